@@ -0,0 +1,1154 @@
+// mod profile;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::collections::UnorderedSet;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, ext_contract, near_bindgen, serde_json, AccountId, Balance, Gas, Promise, PromiseResult, wee_alloc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
+/// This trait provides the baseline of functions as described at:
+/// https://github.com/nearprotocol/NEPs/blob/nep-4/specs/Standards/Tokens/NonFungibleToken.md
+pub trait NEP4 {
+    // Grant the access to the given `accountId` for the given `tokenId`.
+    // Requirements:
+    // * The caller of the function (`predecessor_id`) should have access to the token.
+    fn grant_access(&mut self, escrow_account_id: AccountId);
+
+    // Revoke the access to the given `accountId` for the given `tokenId`.
+    // Requirements:
+    // * The caller of the function (`predecessor_id`) should have access to the token.
+    fn revoke_access(&mut self, escrow_account_id: AccountId);
+
+    // Transfer the given `tokenId` to the given `accountId`. Account `accountId` becomes the new owner.
+    // Requirements:
+    // * The caller of the function (`predecessor_id`) should have access to the token.
+    fn transfer_from(&mut self, owner_id: AccountId, new_owner_id: AccountId, token_id: TokenId);
+
+    // Transfer the given `tokenId` to the given `accountId`. Account `accountId` becomes the new owner.
+    // Requirements:
+    // * The caller of the function (`predecessor_id`) should be the owner of the token. Callers who have
+    // escrow access should use transfer_from.
+    fn transfer(&mut self, new_owner_id: AccountId, token_id: TokenId);
+
+    // Returns `true` or `false` based on caller of the function (`predecessor_id) having access to the token
+    fn check_access(&self, account_id: AccountId) -> bool;
+
+    // Get an individual owner by given `tokenId`.
+    fn get_token_owner(&self, token_id: TokenId) -> String;
+}
+
+/// Contracts receiving tokens through `nft_transfer_call`, as described at:
+/// https://nomicon.io/Standards/Tokens/NonFungibleToken/Core
+#[ext_contract(ext_receiver)]
+pub trait NonFungibleTokenReceiver {
+    // Returns `true` if the token should be returned to `previous_owner_id`.
+    fn nft_on_transfer(&mut self, sender_id: AccountId, previous_owner_id: AccountId, token_id: TokenId, msg: String) -> bool;
+}
+
+#[ext_contract(ext_self)]
+pub trait NonFungibleTokenResolver {
+    fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: TokenId) -> bool;
+}
+
+/// The token ID type is also defined in the NEP
+pub type TokenId = u64;
+pub type AccountIdHash = Vec<u8>;
+
+/// Upper bound of tokens created by a single `mint_batch` call
+const MAX_MINT_BATCH: usize = 50;
+/// Gas reserved for every token created by `mint_batch`
+const GAS_PER_MINT: Gas = 3_000_000_000_000;
+/// Royalties are expressed in basis points, 10_000 is the whole sale price
+const ROYALTY_DENOMINATOR: u32 = 10_000;
+/// Upper bound of the sum of the royalties of a token
+const MAX_TOTAL_ROYALTY: u32 = 5_000;
+
+/// Gas given to the receiver of `nft_transfer_call`
+const GAS_FOR_NFT_ON_TRANSFER: Gas = 40_000_000_000_000;
+/// Gas given to `nft_resolve_transfer` once the receiver is done
+const GAS_FOR_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
+
+/// Share of every resale, in basis points, for each royalty receiver
+pub type Royalty = HashMap<AccountId, u32>;
+
+/// Amounts to send to each account after a sale, as described at:
+/// https://nomicon.io/Standards/Tokens/NonFungibleToken/Payout
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// Descriptive fields of a token, loosely following the NEP-177 token metadata
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct TokenMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub extra: Option<String>,
+    /// Series created with `create_series` the token belongs to
+    pub series: Option<String>,
+}
+
+/// Supply of a series, `minted` never decreases so burning does not free room under the cap
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SeriesSupply {
+    pub cap: Option<u64>,
+    pub minted: u64,
+    pub supply: u64,
+}
+
+// Begin implementation
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct NonFungibleTokenBasic {
+    pub token_to_account: UnorderedMap<TokenId, AccountId>,
+    pub account_gives_access: UnorderedMap<AccountIdHash, UnorderedSet<AccountIdHash>>, // Vec<u8> is sha256 of account, makes it safer and is how fungible token also works
+    pub owner_id: AccountId,
+    pub token_metadata: UnorderedMap<TokenId, TokenMetadata>,
+    pub minters: UnorderedSet<AccountId>,
+    pub next_token_id: TokenId,
+    pub total_supply: u64,
+    pub series_supply: UnorderedMap<String, SeriesSupply>,
+    pub owner_supply: UnorderedMap<AccountIdHash, u64>,
+    pub token_royalty: UnorderedMap<TokenId, Royalty>,
+}
+
+/// State written by the contract before the supply counters, read once by `migrate`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldNonFungibleTokenBasic {
+    pub token_to_account: UnorderedMap<TokenId, AccountId>,
+    pub account_gives_access: UnorderedMap<AccountIdHash, UnorderedSet<AccountIdHash>>,
+    pub owner_id: AccountId,
+}
+
+impl Default for NonFungibleTokenBasic {
+    fn default() -> Self {
+        panic!("NFT should be initialized before usage")
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenBasic {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        assert!(env::is_valid_account_id(owner_id.as_bytes()), "Owner's account ID is invalid.");
+        assert!(!env::state_exists(), "Already initialized");
+        Self {
+            token_to_account: UnorderedMap::new(b"token-belongs-to".to_vec()),
+            account_gives_access: UnorderedMap::new(b"gives-access".to_vec()),
+            owner_id,
+            token_metadata: UnorderedMap::new(b"token-metadata".to_vec()),
+            minters: UnorderedSet::new(b"minters".to_vec()),
+            next_token_id: 0,
+            total_supply: 0,
+            series_supply: UnorderedMap::new(b"series-supply".to_vec()),
+            owner_supply: UnorderedMap::new(b"owner-supply".to_vec()),
+            token_royalty: UnorderedMap::new(b"token-royalty".to_vec()),
+        }
+    }
+
+    /// Upgrades the state of a contract deployed before the supply counters.
+    /// Every token is visited once to fill in the total and per-owner supply.
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can migrate the state.");
+        let old: OldNonFungibleTokenBasic = match env::state_read() {
+            Some(old) => old,
+            None => env::panic(b"No state to migrate.")
+        };
+        let mut contract = Self {
+            token_to_account: old.token_to_account,
+            account_gives_access: old.account_gives_access,
+            owner_id: old.owner_id,
+            token_metadata: UnorderedMap::new(b"token-metadata".to_vec()),
+            minters: UnorderedSet::new(b"minters".to_vec()),
+            next_token_id: 0,
+            total_supply: 0,
+            series_supply: UnorderedMap::new(b"series-supply".to_vec()),
+            owner_supply: UnorderedMap::new(b"owner-supply".to_vec()),
+            token_royalty: UnorderedMap::new(b"token-royalty".to_vec()),
+        };
+        contract.total_supply = contract.token_to_account.len();
+        let mut counts: HashMap<AccountId, u64> = HashMap::new();
+        for owner_id in contract.token_to_account.values() {
+            *counts.entry(owner_id).or_insert(0) += 1;
+        }
+        for (owner_id, count) in counts.iter() {
+            contract.owner_supply.insert(&env::sha256(owner_id.as_bytes()), count);
+        }
+        contract
+    }
+}
+
+#[near_bindgen]
+impl NEP4 for NonFungibleTokenBasic {
+    fn grant_access(&mut self, escrow_account_id: AccountId) {
+        let escrow_hash = env::sha256(escrow_account_id.as_bytes());
+        let predecessor = env::predecessor_account_id();
+        let predecessor_hash = env::sha256(predecessor.as_bytes());
+
+        let mut access_set = match self.account_gives_access.get(&predecessor_hash) {
+            Some(existing_set) => {
+                existing_set
+            },
+            None => {
+                UnorderedSet::new(b"new-access-set".to_vec())
+            }
+        };
+        access_set.insert(&escrow_hash);
+        self.account_gives_access.insert(&predecessor_hash, &access_set);
+    }
+
+    fn revoke_access(&mut self, escrow_account_id: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        let predecessor_hash = env::sha256(predecessor.as_bytes());
+        let mut existing_set = match self.account_gives_access.get(&predecessor_hash) {
+            Some(existing_set) => existing_set,
+            None => env::panic(b"Access does not exist.")
+        };
+        let escrow_hash = env::sha256(escrow_account_id.as_bytes());
+        if existing_set.contains(&escrow_hash) {
+            existing_set.remove(&escrow_hash);
+            self.account_gives_access.insert(&predecessor_hash, &existing_set);
+            env::log(b"Successfully removed access.")
+        } else {
+            env::panic(b"Did not find access for escrow ID.")
+        }
+    }
+
+    fn transfer(&mut self, new_owner_id: AccountId, token_id: TokenId) {
+        let token_owner_account_id = self.get_token_owner(token_id);
+        let predecessor = env::predecessor_account_id();
+        if predecessor != token_owner_account_id {
+            env::panic(b"Attempt to call transfer on tokens belonging to another account.")
+        }
+        self.internal_transfer(&token_owner_account_id, &new_owner_id, token_id);
+    }
+
+    fn transfer_from(&mut self, owner_id: AccountId, new_owner_id: AccountId, token_id: TokenId) {
+        let token_owner_account_id = self.get_token_owner(token_id);
+        if owner_id != token_owner_account_id {
+            env::panic(b"Attempt to transfer a token from a different owner.")
+        }
+
+        if !self.check_access(token_owner_account_id.to_string()) {
+            env::panic(b"Attempt to transfer a token with no access.")
+        }
+        self.internal_transfer(&token_owner_account_id, &new_owner_id, token_id);
+    }
+
+    fn check_access(&self, account_id: AccountId) -> bool {
+        let account_hash = env::sha256(account_id.as_bytes());
+        let predecessor = env::predecessor_account_id();
+        if predecessor == account_id {
+            return true;
+        }
+        match self.account_gives_access.get(&account_hash) {
+            Some(access) => {
+                let predecessor = env::predecessor_account_id();
+                let predecessor_hash = env::sha256(predecessor.as_bytes());
+                access.contains(&predecessor_hash)
+            },
+            None => false
+        }
+    }
+
+    fn get_token_owner(&self, token_id: TokenId) -> String {
+        match self.token_to_account.get(&token_id) {
+            Some(owner_id) => owner_id,
+            None => env::panic(b"No owner of the token ID specified")
+        }
+    }
+}
+
+/// Methods not in the strict scope of the NFT spec (NEP4)
+#[near_bindgen]
+impl NonFungibleTokenBasic {
+    /// Creates a token for owner_id, doesn't use autoincrement, fails if id is taken
+    pub fn mint_token(&mut self, owner_id: String, token_id: TokenId) {
+        // make sure that only the owner can call this funtion
+        self.only_owner();
+        // Since Map doesn't have `contains` we use match
+        let token_check = self.token_to_account.get(&token_id);
+        if token_check.is_some() {
+            env::panic(b"Token ID already exists.")
+        }
+        // No token with that ID exists, mint and add token to data structures
+        self.internal_add_token(&owner_id, token_id, None);
+    }
+
+    /// Creates a token for owner_id with the next free ID, returns the ID of the new token
+    pub fn mint_next(&mut self, owner_id: AccountId, metadata: TokenMetadata, royalty: Option<Royalty>) -> TokenId {
+        self.only_minter();
+        let token_id = self.internal_mint_next(owner_id, metadata);
+        if let Some(royalty) = royalty {
+            self.internal_set_royalty(token_id, royalty);
+        }
+        token_id
+    }
+
+    /// Creates a token for every (owner_id, metadata) pair, returns the IDs in the same order.
+    /// The royalty, if any, applies to every token of the batch.
+    pub fn mint_batch(&mut self, tokens: Vec<(AccountId, TokenMetadata)>, royalty: Option<Royalty>) -> Vec<TokenId> {
+        self.only_minter();
+        assert!(tokens.len() <= MAX_MINT_BATCH, "Too many tokens in one batch, the maximum is {}.", MAX_MINT_BATCH);
+        assert!(
+            env::prepaid_gas() - env::used_gas() >= GAS_PER_MINT * tokens.len() as Gas,
+            "Not enough gas attached to mint {} tokens.", tokens.len()
+        );
+        let token_ids: Vec<TokenId> = tokens.into_iter()
+            .map(|(owner_id, metadata)| self.internal_mint_next(owner_id, metadata))
+            .collect();
+        if let Some(royalty) = royalty {
+            for token_id in &token_ids {
+                self.internal_set_royalty(*token_id, royalty.clone());
+            }
+        }
+        token_ids
+    }
+
+    /// Allows account_id to mint tokens with `mint_next` and `mint_batch`
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.only_owner();
+        assert!(env::is_valid_account_id(account_id.as_bytes()), "Minter's account ID is invalid.");
+        self.minters.insert(&account_id);
+    }
+
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.only_owner();
+        if !self.minters.remove(&account_id) {
+            env::panic(b"Account is not a minter.")
+        }
+    }
+
+    pub fn is_minter(&self, account_id: AccountId) -> bool {
+        account_id == self.owner_id || self.minters.contains(&account_id)
+    }
+
+    pub fn get_token_metadata(&self, token_id: TokenId) -> Option<TokenMetadata> {
+        self.token_metadata.get(&token_id)
+    }
+
+    /// Destroys the token, callable by its owner or an account the owner gave access to
+    pub fn nft_burn(&mut self, token_id: TokenId) {
+        let token_owner_account_id = self.get_token_owner(token_id);
+        if !self.check_access(token_owner_account_id.to_string()) {
+            env::panic(b"Attempt to burn a token with no access.")
+        }
+
+        self.token_to_account.remove(&token_id);
+        self.token_royalty.remove(&token_id);
+        self.total_supply -= 1;
+        if let Some(metadata) = self.token_metadata.remove(&token_id) {
+            if let Some(series_id) = metadata.series {
+                let mut series = self.series_supply.get(&series_id).unwrap();
+                series.supply -= 1;
+                self.series_supply.insert(&series_id, &series);
+            }
+        }
+        self.internal_change_owner_supply(&token_owner_account_id, false);
+        env::log(format!("burned token {} of {}", token_id, token_owner_account_id).as_bytes());
+    }
+
+    /// Creates a series of tokens, `cap` limits how many tokens can ever be minted in it
+    pub fn create_series(&mut self, series_id: String, cap: Option<u64>) {
+        self.only_owner();
+        if self.series_supply.get(&series_id).is_some() {
+            env::panic(b"Series already exists.")
+        }
+        self.series_supply.insert(&series_id, &SeriesSupply { cap, minted: 0, supply: 0 });
+    }
+
+    pub fn get_series(&self, series_id: String) -> Option<SeriesSupply> {
+        self.series_supply.get(&series_id)
+    }
+
+    pub fn nft_total_supply(&self) -> u64 {
+        self.total_supply
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> u64 {
+        self.owner_supply.get(&env::sha256(account_id.as_bytes())).unwrap_or(0)
+    }
+
+    fn internal_mint_next(&mut self, owner_id: AccountId, metadata: TokenMetadata) -> TokenId {
+        assert!(env::is_valid_account_id(owner_id.as_bytes()), "Owner's account ID is invalid.");
+        // skip IDs already taken through `mint_token`
+        while self.token_to_account.get(&self.next_token_id).is_some() {
+            self.next_token_id += 1;
+        }
+        let token_id = self.next_token_id;
+        self.next_token_id += 1;
+        self.internal_add_token(&owner_id, token_id, Some(metadata));
+        env::log(format!("minted token {} for {}", token_id, owner_id).as_bytes());
+        token_id
+    }
+
+    /// Stores a new token and keeps the supply counters up to date
+    fn internal_add_token(&mut self, owner_id: &AccountId, token_id: TokenId, metadata: Option<TokenMetadata>) {
+        if let Some(metadata) = metadata {
+            if let Some(series_id) = &metadata.series {
+                let mut series = match self.series_supply.get(series_id) {
+                    Some(series) => series,
+                    None => env::panic(b"Series does not exist.")
+                };
+                if series.cap.is_some_and(|cap| series.minted >= cap) {
+                    env::panic(b"Series supply cap reached.")
+                }
+                series.minted += 1;
+                series.supply += 1;
+                self.series_supply.insert(series_id, &series);
+            }
+            self.token_metadata.insert(&token_id, &metadata);
+        }
+        self.token_to_account.insert(&token_id, owner_id);
+        self.total_supply += 1;
+        self.internal_change_owner_supply(owner_id, true);
+    }
+
+    fn internal_set_royalty(&mut self, token_id: TokenId, royalty: Royalty) {
+        let mut total: u32 = 0;
+        for (account_id, share) in royalty.iter() {
+            assert!(env::is_valid_account_id(account_id.as_bytes()), "Royalty account ID is invalid.");
+            total = total.saturating_add(*share);
+        }
+        assert!(total <= MAX_TOTAL_ROYALTY, "Royalties can not exceed {} basis points in total.", MAX_TOTAL_ROYALTY);
+        self.token_royalty.insert(&token_id, &royalty);
+    }
+
+    fn internal_transfer(&mut self, owner_id: &AccountId, new_owner_id: &AccountId, token_id: TokenId) {
+        self.token_to_account.insert(&token_id, new_owner_id);
+        self.internal_change_owner_supply(owner_id, false);
+        self.internal_change_owner_supply(new_owner_id, true);
+    }
+
+    /// Adds or removes one token from the account's count, returns the new count
+    fn internal_change_owner_supply(&mut self, account_id: &AccountId, increase: bool) {
+        let account_hash = env::sha256(account_id.as_bytes());
+        let count = self.owner_supply.get(&account_hash).unwrap_or(0);
+        let count = if increase { count + 1 } else { count - 1 };
+        if count == 0 {
+            self.owner_supply.remove(&account_hash);
+        } else {
+            self.owner_supply.insert(&account_hash, &count);
+        }
+    }
+
+    /// helper function determining contract ownership
+    fn only_owner(&mut self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only contract owner can call this method.");
+    }
+
+    /// helper function determining minting rights, the owner is always a minter
+    fn only_minter(&self) {
+        assert!(self.is_minter(env::predecessor_account_id()), "Only contract owner or minters can call this method.");
+    }
+}
+
+/// Royalties and payouts as described at:
+/// https://nomicon.io/Standards/Tokens/NonFungibleToken/Payout
+#[near_bindgen]
+impl NonFungibleTokenBasic {
+    pub fn get_token_royalty(&self, token_id: TokenId) -> Royalty {
+        self.token_royalty.get(&token_id).unwrap_or_default()
+    }
+
+    /// Splits `balance` between the royalty receivers and the current owner of the token
+    pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let owner_id = self.get_token_owner(token_id);
+        let royalty = self.get_token_royalty(token_id);
+        let mut payout: HashMap<AccountId, U128> = HashMap::new();
+        let mut paid: Balance = 0;
+        for (account_id, share) in royalty.into_iter() {
+            if account_id == owner_id {
+                continue;
+            }
+            let amount = royalty_share(balance.0, share);
+            paid += amount;
+            payout.insert(account_id, U128(amount));
+        }
+        payout.insert(owner_id, U128(balance.0 - paid));
+        if let Some(max_len_payout) = max_len_payout {
+            assert!(payout.len() as u32 <= max_len_payout, "Payout has more than {} receivers.", max_len_payout);
+        }
+        Payout { payout }
+    }
+
+    /// Transfers the token like `transfer_from` and returns the payout of the sale to the caller.
+    /// Escrow access covers every token of the owner, so there is no `approval_id` to check.
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        assert_one_yocto();
+        assert!(approval_id.is_none(), "Approval IDs are not supported, grant access to the escrow account instead.");
+        let payout = self.nft_payout(token_id, balance, max_len_payout);
+        let owner_id = self.get_token_owner(token_id);
+        self.transfer_from(owner_id.clone(), receiver_id.clone(), token_id);
+        if let Some(memo) = memo {
+            env::log(format!("token {} sold by {} to {}: {}", token_id, owner_id, receiver_id, memo).as_bytes());
+        }
+        payout
+    }
+}
+
+/// Part of `balance` owed for `share` basis points, rounded down without overflowing
+fn royalty_share(balance: Balance, share: u32) -> Balance {
+    let denominator = ROYALTY_DENOMINATOR as Balance;
+    let share = share as Balance;
+    balance / denominator * share + balance % denominator * share / denominator
+}
+
+/// Transfer and call as described at:
+/// https://nomicon.io/Standards/Tokens/NonFungibleToken/Core
+#[near_bindgen]
+impl NonFungibleTokenBasic {
+    /// Transfers the token to the `receiver_id` contract and calls its `nft_on_transfer`.
+    /// The token goes back to its previous owner if the receiver fails or asks for it.
+    pub fn nft_transfer_call(&mut self, receiver_id: AccountId, token_id: TokenId, msg: String) -> Promise {
+        let previous_owner_id = self.get_token_owner(token_id);
+        if !self.check_access(previous_owner_id.to_string()) {
+            env::panic(b"Attempt to transfer a token with no access.")
+        }
+        self.internal_transfer(&previous_owner_id, &receiver_id, token_id);
+        ext_receiver::nft_on_transfer(
+            env::predecessor_account_id(),
+            previous_owner_id.to_string(),
+            token_id,
+            msg,
+            &receiver_id,
+            0,
+            GAS_FOR_NFT_ON_TRANSFER,
+        ).then(ext_self::nft_resolve_transfer(
+            previous_owner_id,
+            receiver_id,
+            token_id,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_TRANSFER,
+        ))
+    }
+
+    /// Returns `true` if the token stays with the receiver
+    #[private]
+    pub fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: TokenId) -> bool {
+        let should_return = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(true),
+            _ => true,
+        };
+        if !should_return {
+            return true;
+        }
+
+        // the receiver may have already moved or burned the token
+        if self.token_to_account.get(&token_id) != Some(receiver_id.to_string()) {
+            return true;
+        }
+        self.internal_transfer(&receiver_id, &previous_owner_id, token_id);
+        env::log(format!("token {} returned to {}", token_id, previous_owner_id).as_bytes());
+        false
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+// the baseline tests keep their original style
+#[allow(clippy::bool_assert_comparison, clippy::clone_on_copy)]
+mod tests {
+    use super::*;
+    use near_sdk::MockedBlockchain;
+    use near_sdk::{testing_env, VMContext};
+
+    fn joe() -> AccountId {
+        "joe.testnet".to_string()
+    }
+    fn robert() -> AccountId {
+        "robert.testnet".to_string()
+    }
+    fn mike() -> AccountId {
+        "mike.testnet".to_string()
+    }
+
+    // part of writing unit tests is setting up a mock context
+    // this is a useful list to peek at when wondering what's available in env::*
+    fn get_context(predecessor_account_id: String, storage_usage: u64) -> VMContext {
+        VMContext {
+            current_account_id: "alice.testnet".to_string(),
+            signer_account_id: "jane.testnet".to_string(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id,
+            input: vec![],
+            block_index: 0,
+            block_timestamp: 0,
+            account_balance: 0,
+            account_locked_balance: 0,
+            storage_usage,
+            attached_deposit: 0,
+            prepaid_gas: 10u64.pow(18),
+            random_seed: vec![0, 1, 2],
+            is_view: false,
+            output_data_receivers: vec![],
+            epoch_height: 19,
+        }
+    }
+
+    #[test]
+    fn grant_access() {
+        let context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let length_before = contract.account_gives_access.len();
+        assert_eq!(0, length_before, "Expected empty account access Map.");
+        contract.grant_access(mike());
+        contract.grant_access(joe());
+        let length_after = contract.account_gives_access.len();
+        assert_eq!(1, length_after, "Expected an entry in the account's access Map.");
+        let predecessor_hash = env::sha256(robert().as_bytes());
+        let num_grantees = contract.account_gives_access.get(&predecessor_hash).unwrap();
+        assert_eq!(2, num_grantees.len(), "Expected two accounts to have access to predecessor.");
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Access does not exist."#
+    )]
+    fn revoke_access_and_panic() {
+        let context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.revoke_access(joe());
+    }
+
+    #[test]
+    fn add_revoke_access_and_check() {
+        // Joe grants access to Robert
+        let mut context = get_context(joe(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(joe());
+        contract.grant_access(robert());
+
+        // does Robert have access to Joe's account? Yes.
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        let mut robert_has_access = contract.check_access(joe());
+        assert_eq!(true, robert_has_access, "After granting access, check_access call failed.");
+
+        // Joe revokes access from Robert
+        context = get_context(joe(), env::storage_usage());
+        testing_env!(context);
+        contract.revoke_access(robert());
+
+        // does Robert have access to Joe's account? No
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        robert_has_access = contract.check_access(joe());
+        assert_eq!(false, robert_has_access, "After revoking access, check_access call failed.");
+    }
+
+    #[test]
+    fn mint_token_get_token_owner() {
+        let context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_token(mike(), 19u64);
+        let owner = contract.get_token_owner(19u64);
+        assert_eq!(mike(), owner, "Unexpected token owner.");
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Attempt to transfer a token with no access."#
+    )]
+    fn transfer_from_with_no_access_should_fail() {
+        // Mike owns the token.
+        // Robert is trying to transfer it to Robert's account without having access.
+        let context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let token_id = 19u64;
+        contract.mint_token(mike(), token_id);
+        contract.transfer_from(mike(), robert(), token_id.clone());
+    }
+
+    #[test]
+    fn transfer_from_with_escrow_access() {
+        // Escrow account: robert.testnet
+        // Owner account: mike.testnet
+        // New owner account: joe.testnet
+        let mut context = get_context(mike(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        let token_id = 19u64;
+        contract.mint_token(mike(), token_id);
+        // Mike grants access to Robert
+        contract.grant_access(robert());
+
+        // Robert transfers the token to Joe
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        contract.transfer_from(mike(), joe(), token_id.clone());
+
+        // Check new owner
+        let owner = contract.get_token_owner(token_id.clone());
+        assert_eq!(joe(), owner, "Token was not transferred after transfer call with escrow.");
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Attempt to transfer a token from a different owner."#
+    )]
+    fn transfer_from_with_escrow_access_wrong_owner_id() {
+        // Escrow account: robert.testnet
+        // Owner account: mike.testnet
+        // New owner account: joe.testnet
+        let mut context = get_context(mike(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        let token_id = 19u64;
+        contract.mint_token(mike(), token_id);
+        // Mike grants access to Robert
+        contract.grant_access(robert());
+
+        // Robert transfers the token to Joe
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        contract.transfer_from(robert(), joe(), token_id.clone());
+    }
+
+    #[test]
+    fn transfer_from_with_your_own_token() {
+        // Owner account: robert.testnet
+        // New owner account: joe.testnet
+
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let token_id = 19u64;
+        contract.mint_token(robert(), token_id);
+
+        // Robert transfers the token to Joe
+        contract.transfer_from(robert(), joe(), token_id.clone());
+
+        // Check new owner
+        let owner = contract.get_token_owner(token_id.clone());
+        assert_eq!(joe(), owner, "Token was not transferred after transfer call with escrow.");
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Attempt to call transfer on tokens belonging to another account."#
+    )]
+    fn transfer_with_escrow_access_fails() {
+        // Escrow account: robert.testnet
+        // Owner account: mike.testnet
+        // New owner account: joe.testnet
+        let mut context = get_context(mike(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        let token_id = 19u64;
+        contract.mint_token(mike(), token_id);
+        // Mike grants access to Robert
+        contract.grant_access(robert());
+
+        // Robert transfers the token to Joe
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        contract.transfer(joe(), token_id.clone());
+    }
+
+    #[test]
+    fn transfer_with_your_own_token() {
+        // Owner account: robert.testnet
+        // New owner account: joe.testnet
+
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let token_id = 19u64;
+        contract.mint_token(robert(), token_id);
+
+        // Robert transfers the token to Joe
+        contract.transfer(joe(), token_id.clone());
+
+        // Check new owner
+        let owner = contract.get_token_owner(token_id.clone());
+        assert_eq!(joe(), owner, "Token was not transferred after transfer call with escrow.");
+    }
+
+    fn sword() -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Sword".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mint_next_assigns_ids_from_counter() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_token(mike(), 1u64);
+        assert_eq!(0, contract.mint_next(joe(), sword(), None));
+        // 1 was taken by mint_token
+        assert_eq!(2, contract.mint_next(mike(), TokenMetadata::default(), None));
+        assert_eq!(joe(), contract.get_token_owner(0));
+        assert_eq!(mike(), contract.get_token_owner(2));
+        assert_eq!(Some(sword()), contract.get_token_metadata(0));
+        assert_eq!(None, contract.get_token_metadata(1));
+    }
+
+    #[test]
+    fn mint_batch_by_minter() {
+        let mut context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.add_minter(mike());
+        assert!(contract.is_minter(mike()));
+        assert!(contract.is_minter(robert()));
+        assert!(!contract.is_minter(joe()));
+
+        context = get_context(mike(), env::storage_usage());
+        testing_env!(context);
+        let token_ids = contract.mint_batch(vec![(joe(), sword()), (mike(), sword()), (joe(), TokenMetadata::default())], None);
+        assert_eq!(vec![0, 1, 2], token_ids);
+        assert_eq!(joe(), contract.get_token_owner(2));
+        assert_eq!(mike(), contract.get_token_owner(1));
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Only contract owner or minters can call this method."#
+    )]
+    fn mint_next_after_minter_removed() {
+        let mut context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.add_minter(mike());
+        contract.remove_minter(mike());
+
+        context = get_context(mike(), env::storage_usage());
+        testing_env!(context);
+        contract.mint_next(mike(), sword(), None);
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Too many tokens in one batch, the maximum is 50."#
+    )]
+    fn mint_batch_too_large() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_batch(vec![(joe(), TokenMetadata::default()); MAX_MINT_BATCH + 1], None);
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Not enough gas attached to mint 10 tokens."#
+    )]
+    fn mint_batch_not_enough_gas() {
+        let mut context = get_context(robert(), 0);
+        context.prepaid_gas = GAS_PER_MINT * 5;
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_batch(vec![(joe(), TokenMetadata::default()); 10], None);
+    }
+
+    #[test]
+    fn supply_follows_mint_transfer_and_burn() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.create_series("swords".to_string(), None);
+        let mut metadata = sword();
+        metadata.series = Some("swords".to_string());
+        contract.mint_token(mike(), 19u64);
+        contract.mint_batch(vec![(mike(), metadata.clone()), (robert(), metadata)], None);
+        assert_eq!(3, contract.nft_total_supply());
+        assert_eq!(2, contract.nft_supply_for_owner(mike()));
+        assert_eq!(2, contract.get_series("swords".to_string()).unwrap().supply);
+
+        contract.transfer(joe(), 1u64);
+        assert_eq!(3, contract.nft_total_supply());
+        assert_eq!(0, contract.nft_supply_for_owner(robert()));
+        assert_eq!(1, contract.nft_supply_for_owner(joe()));
+
+        testing_env!(get_context(joe(), env::storage_usage()));
+        contract.nft_burn(1u64);
+        assert_eq!(2, contract.nft_total_supply());
+        assert_eq!(0, contract.nft_supply_for_owner(joe()));
+        assert_eq!(None, contract.get_token_metadata(1u64));
+        let series = contract.get_series("swords".to_string()).unwrap();
+        assert_eq!(1, series.supply);
+        assert_eq!(2, series.minted);
+    }
+
+    #[test]
+    fn migrate_fills_in_supply_of_existing_tokens() {
+        let context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut old = OldNonFungibleTokenBasic {
+            token_to_account: UnorderedMap::new(b"token-belongs-to".to_vec()),
+            account_gives_access: UnorderedMap::new(b"gives-access".to_vec()),
+            owner_id: robert(),
+        };
+        old.token_to_account.insert(&1, &mike());
+        old.token_to_account.insert(&2, &mike());
+        old.token_to_account.insert(&7, &joe());
+        env::state_write(&old);
+
+        let context = get_context("alice.testnet".to_string(), env::storage_usage());
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::migrate();
+        assert_eq!(3, contract.nft_total_supply());
+        assert_eq!(2, contract.nft_supply_for_owner(mike()));
+        assert_eq!(1, contract.nft_supply_for_owner(joe()));
+
+        let context = get_context(mike(), env::storage_usage());
+        testing_env!(context);
+        contract.nft_burn(1);
+        contract.transfer(joe(), 2);
+        assert_eq!(2, contract.nft_total_supply());
+        assert_eq!(0, contract.nft_supply_for_owner(mike()));
+        assert_eq!(2, contract.nft_supply_for_owner(joe()));
+    }
+
+    #[test]
+    fn burn_with_escrow_access_keeps_access() {
+        let mut context = get_context(mike(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        contract.mint_token(mike(), 19u64);
+        contract.grant_access(robert());
+        testing_env!(get_context(joe(), env::storage_usage()));
+        contract.grant_access(robert());
+
+        // Robert burns the only token of Mike
+        context = get_context(robert(), env::storage_usage());
+        testing_env!(context);
+        contract.nft_burn(19u64);
+        assert_eq!(0, contract.nft_total_supply());
+        assert_eq!(0, contract.nft_supply_for_owner(mike()));
+        // the access is given per account, not per token
+        assert!(contract.check_access(mike()));
+        assert!(contract.check_access(joe()));
+        assert_eq!(2, contract.account_gives_access.len());
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Attempt to burn a token with no access."#
+    )]
+    fn burn_with_no_access_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_token(mike(), 19u64);
+        contract.nft_burn(19u64);
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Series supply cap reached."#
+    )]
+    fn mint_beyond_series_cap_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.create_series("crowns".to_string(), Some(2));
+        let metadata = TokenMetadata {
+            series: Some("crowns".to_string()),
+            ..Default::default()
+        };
+        contract.mint_next(joe(), metadata.clone(), None);
+        let token_id = contract.mint_next(joe(), metadata.clone(), None);
+        // burning does not make room for another token
+        testing_env!(get_context(joe(), env::storage_usage()));
+        contract.nft_burn(token_id);
+        testing_env!(get_context(robert(), env::storage_usage()));
+        contract.mint_next(mike(), metadata, None);
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Series does not exist."#
+    )]
+    fn mint_in_unknown_series_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        contract.mint_next(joe(), TokenMetadata {
+            series: Some("crowns".to_string()),
+            ..Default::default()
+        }, None);
+    }
+
+    fn payout_of(payout: &Payout, account_id: AccountId) -> u128 {
+        payout.payout.get(&account_id).map_or(0, |amount| amount.0)
+    }
+
+    #[test]
+    fn payout_without_royalty() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let token_id = contract.mint_next(mike(), sword(), None);
+        let payout = contract.nft_payout(token_id, U128(1_000), Some(1));
+        assert_eq!(1, payout.payout.len());
+        assert_eq!(1_000, payout_of(&payout, mike()));
+    }
+
+    #[test]
+    fn payout_with_several_royalties() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 1_000);
+        royalty.insert(joe(), 250);
+        let token_id = contract.mint_next(mike(), sword(), Some(royalty));
+        let payout = contract.nft_payout(token_id, U128(10_003), Some(3));
+        assert_eq!(1_000, payout_of(&payout, robert()));
+        assert_eq!(250, payout_of(&payout, joe()));
+        // the owner gets the rest, including the rounding
+        assert_eq!(8_753, payout_of(&payout, mike()));
+    }
+
+    #[test]
+    fn payout_when_owner_is_a_royalty_receiver() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 5_000);
+        let token_ids = contract.mint_batch(vec![(robert(), sword()), (mike(), sword())], Some(royalty));
+        let payout = contract.nft_payout(token_ids[0], U128(100), Some(1));
+        assert_eq!(100, payout_of(&payout, robert()));
+        let payout = contract.nft_payout(token_ids[1], U128(100), Some(2));
+        assert_eq!(50, payout_of(&payout, robert()));
+        assert_eq!(50, payout_of(&payout, mike()));
+    }
+
+    #[test]
+    fn transfer_payout_moves_token() {
+        let mut context = get_context(robert(), 0);
+        testing_env!(context);
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 500);
+        let token_id = contract.mint_next(mike(), sword(), Some(royalty));
+
+        // Mike sells the token to Joe through the marketplace Robert
+        context = get_context(mike(), env::storage_usage());
+        testing_env!(context);
+        contract.grant_access(robert());
+        context = get_context(robert(), env::storage_usage());
+        context.attached_deposit = 1;
+        testing_env!(context);
+        let payout = contract.nft_transfer_payout(joe(), token_id, None, Some("sale".to_string()), U128(2_000), Some(2));
+        assert_eq!(100, payout_of(&payout, robert()));
+        assert_eq!(1_900, payout_of(&payout, mike()));
+        assert_eq!(joe(), contract.get_token_owner(token_id));
+
+        // royalties stay with the token after the sale
+        let payout = contract.nft_payout(token_id, U128(2_000), None);
+        assert_eq!(100, payout_of(&payout, robert()));
+        assert_eq!(1_900, payout_of(&payout, joe()));
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Payout has more than 2 receivers."#
+    )]
+    fn payout_longer_than_max_len_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 100);
+        royalty.insert(joe(), 100);
+        let token_id = contract.mint_next(mike(), sword(), Some(royalty));
+        contract.nft_payout(token_id, U128(1_000), Some(2));
+    }
+
+    #[test]
+    fn payout_of_the_largest_balance() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 5_000);
+        let token_id = contract.mint_next(mike(), sword(), Some(royalty));
+        let payout = contract.nft_payout(token_id, U128(u128::MAX), None);
+        assert_eq!(u128::MAX / 2, payout_of(&payout, robert()));
+        assert_eq!(u128::MAX - u128::MAX / 2, payout_of(&payout, mike()));
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Requires attached deposit of exactly 1 yoctoNEAR"#
+    )]
+    fn transfer_payout_without_deposit_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let token_id = contract.mint_next(robert(), sword(), None);
+        contract.nft_transfer_payout(joe(), token_id, None, None, U128(1_000), None);
+    }
+
+    #[test]
+    #[should_panic(
+    expected = r#"Royalties can not exceed 5000 basis points in total."#
+    )]
+    fn royalty_above_cap_should_fail() {
+        testing_env!(get_context(robert(), 0));
+        let mut contract = NonFungibleTokenBasic::new(robert());
+        let mut royalty = Royalty::new();
+        royalty.insert(robert(), 3_000);
+        royalty.insert(joe(), 2_001);
+        contract.mint_next(mike(), sword(), Some(royalty));
+    }
+
+    #[test]
+    fn transfer_call_moves_token_to_receiver() {
+        testing_env!(get_context(mike(), 0));
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        contract.mint_token(mike(), 19u64);
+        contract.nft_transfer_call(joe(), 19u64, String::new());
+        assert_eq!(joe(), contract.get_token_owner(19u64));
+        assert_eq!(0, contract.nft_supply_for_owner(mike()));
+    }
+
+    fn resolve_context(promise_result: PromiseResult) {
+        testing_env!(
+            get_context("alice.testnet".to_string(), env::storage_usage()),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
+    #[test]
+    fn resolve_transfer_keeps_or_returns_token() {
+        testing_env!(get_context(mike(), 0));
+        let mut contract = NonFungibleTokenBasic::new(mike());
+        contract.mint_token(mike(), 19u64);
+        contract.mint_token(mike(), 20u64);
+        contract.mint_token(mike(), 21u64);
+        contract.nft_transfer_call(joe(), 19u64, String::new());
+        contract.nft_transfer_call(joe(), 20u64, String::new());
+        contract.nft_transfer_call(joe(), 21u64, String::new());
+
+        resolve_context(PromiseResult::Successful(b"false".to_vec()));
+        assert!(contract.nft_resolve_transfer(mike(), joe(), 19u64));
+        assert_eq!(joe(), contract.get_token_owner(19u64));
+
+        resolve_context(PromiseResult::Successful(b"true".to_vec()));
+        assert!(!contract.nft_resolve_transfer(mike(), joe(), 20u64));
+        assert_eq!(mike(), contract.get_token_owner(20u64));
+
+        resolve_context(PromiseResult::Failed);
+        assert!(!contract.nft_resolve_transfer(mike(), joe(), 21u64));
+        assert_eq!(mike(), contract.get_token_owner(21u64));
+        assert_eq!(2, contract.nft_supply_for_owner(mike()));
+        assert_eq!(1, contract.nft_supply_for_owner(joe()));
+    }
+}
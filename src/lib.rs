@@ -1,16 +1,22 @@
+#![allow(clippy::len_zero, clippy::needless_range_loop, clippy::needless_return)]
 extern crate core;
 
+use std::collections::{HashMap, HashSet};
 use chrono::Utc;
 use near_sdk::init;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{AccountId, env, near_bindgen};
-use near_sdk::collections::{UnorderedMap};
-use serde::{Serialize, Serializer};
+use near_sdk::{AccountId, Balance, Gas, Promise, PromiseOrValue, PromiseResult, env, ext_contract, near_bindgen, serde_json};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base58PublicKey, Base64VecU8, U128, U64};
+use near_sdk::serde_json::json;
+use serde::{Deserialize, Serialize, Serializer};
 use serde::ser::SerializeStruct;
+use unicode_normalization::UnicodeNormalization;
+use ed25519_dalek::Verifier;
 
 near_sdk::setup_alloc!();
 
-trait NEP4 {
+pub trait NEP4 {
     // Grant the access to the given `accountId` for the given `tokenId`.
     // Requirements:
     // * The caller of the function (`predecessor_id`) should have access to the token.
@@ -25,11 +31,443 @@ trait NEP4 {
     fn check_access(&self, account_id: AccountId) -> bool;
 }
 
-trait LeaderBoard {
-    fn get_top_players(&self) -> Vec<(String, Profile)>;
+pub trait LeaderBoard {
+    fn get_top_players(&self) -> Vec<LeaderBoardEntry>;
     fn get_reward(&mut self, account_id: AccountId) -> i32;
     fn is_recently_rewarded(&self, account_id: AccountId) -> bool;
-    fn save_new_score(&mut self, account_id: AccountId, score: i32) -> bool;
+    // Save the score of the given character of the account, its first character when `None`.
+    // Same as `submit_score` on the default board without replay.
+    // Requirements:
+    // * Only the owner or a reporter can save scores, see `add_reporter`.
+    // * No game server key should be registered, see `submit_attested_score`.
+    fn save_new_score(&mut self, account_id: AccountId, character: Option<String>, score: i32) -> bool;
+}
+
+pub trait ScoreHistory {
+    // Save the score of a play on the given board, returns `true` when it is the new best score of the character.
+    // A score breaking the rules of the board is not saved, a `score_rejected` event gives its code, see `set_board_rules`.
+    // A suspicious score is not saved until a moderator approves it, see `set_outlier_rules`.
+    // The replay of the play can be referenced by its SHA-256 and an off-chain CID.
+    // A new best score in the top of the ranking stays provisional during the challenge window, see `set_challenge_rules`.
+    // A character has one provisional score per board, a higher one replaces it unless it is disputed.
+    // Every saved score is kept in the recent history of the character once confirmed, see `set_history_depth`.
+    // Only the default board ranks the profile in `get_top_players`, each board has its own `get_board_leaderboard`.
+    // Players submit their own scores with `submit_session_score`, `submit_attested_score` or `reveal_score`.
+    // Requirements:
+    // * Only the owner or a reporter can submit scores directly, see `add_reporter`.
+    // * No game server key should be registered, the scores are then signed, see `add_server_key`.
+    // * `replay_hash` should be the hex encoded SHA-256 of the replay.
+    // * A character can play at most 20 boards.
+    fn submit_score(&mut self, account_id: AccountId, character: Option<String>, board_id: String, score: i32, replay: Option<ReplayProof>) -> bool;
+
+    // Recent submissions of the character, newest first.
+    fn get_score_history(&self, account_id: AccountId, character: Option<String>, from: u64, limit: u64) -> Vec<ScoreRecord>;
+
+    // Best score of the character on each board it played.
+    fn get_personal_bests(&self, account_id: AccountId, character: Option<String>) -> HashMap<String, i32>;
+
+    // First, best and latest scores of the character on the board.
+    fn get_score_improvement(&self, account_id: AccountId, character: Option<String>, board_id: String) -> Option<ScoreImprovement>;
+
+    // Characters by their best score on the board, hidden ranks excluded.
+    fn get_board_leaderboard(&self, board_id: String, from: u64, limit: u64) -> Vec<LeaderBoardEntry>;
+}
+
+pub trait ScoreAttestations {
+    // Save the score of an attestation signed by a game server, like `submit_score` without replay.
+    // The signature is the ed25519 signature of the Borsh serialized tuple of this contract account id and the attestation.
+    // Requirements:
+    // * The caller (`predecessor_id`) should be the account of the attestation, it pays for the gas.
+    // * The signing key should be registered, see `add_server_key`.
+    // * The attestation should not be expired or already used.
+    fn submit_attested_score(&mut self, attestation: ScoreAttestation, character: Option<String>, signature: Base64VecU8) -> bool;
+
+    // Forget up to `limit` used nonces of expired attestations, returns how many were forgotten.
+    fn clean_used_nonces(&mut self, limit: u64) -> u64;
+}
+
+pub trait CommitReveal {
+    // Commit to a score on a commit-reveal board before its commit deadline, see `set_commit_reveal`.
+    // `hash` is the hex encoded SHA-256 of the Borsh serialized `(score: i32, salt: String, account_id: String)`,
+    // where `account_id` is the caller (`predecessor_id`). A new commit replaces the previous one of the character.
+    fn commit_score(&mut self, board_id: String, character: Option<String>, hash: String);
+
+    // Reveal the committed score after the commit deadline and before the reveal deadline,
+    // it is then saved like `submit_score` on the board of the attestation.
+    // Requirements:
+    // * The score should be attested by a game server, the attestation is checked like in `submit_attested_score`.
+    fn reveal_score(&mut self, attestation: ScoreAttestation, character: Option<String>, salt: String, signature: Base64VecU8, replay: Option<ReplayProof>) -> bool;
+
+    // Remove up to `limit` commits past their reveal deadline, returns how many were removed.
+    fn clean_expired_commits(&mut self, limit: u64) -> u64;
+
+    fn get_score_commit(&self, account_id: AccountId, character: Option<String>, board_id: String) -> Option<ScoreCommit>;
+}
+
+pub trait ScoreChallenges {
+    // Dispute a provisional score during its challenge window, see `set_challenge_rules`.
+    // Requirements:
+    // * The attached deposit should be at least the dispute bond.
+    // * The score should not be disputed already.
+    fn dispute_score(&mut self, score_id: u64, reason: String);
+
+    // Remove the disputed score and return the bond to the challenger, or confirm the score and keep the bond.
+    // Requirements:
+    // * Only moderators can resolve disputes, see `add_moderator`.
+    fn resolve_dispute(&mut self, score_id: u64, remove: bool);
+
+    // Rank an undisputed provisional score after its challenge window, returns `true` if it is still the best score of the character.
+    fn finalize_score(&mut self, score_id: u64) -> bool;
+
+    fn get_provisional_score(&self, score_id: u64) -> Option<ProvisionalScore>;
+
+    fn get_provisional_scores(&self, from: u64, limit: u64) -> Vec<(u64, ProvisionalScore)>;
+}
+
+pub trait ScoreReviews {
+    // Save a score held for review like `submit_score`, it may then stay provisional during the challenge window.
+    // Requirements:
+    // * Only moderators can review scores, see `add_moderator`.
+    fn approve_score(&mut self, review_id: u64) -> bool;
+
+    // Drop a score held for review, it is not saved in the score history.
+    fn reject_score(&mut self, review_id: u64);
+
+    fn get_pending_review(&self, review_id: u64) -> Option<PendingReview>;
+
+    fn get_pending_reviews(&self, from: u64, limit: u64) -> Vec<(u64, PendingReview)>;
+
+    // Count, mean, variance and histogram of the scores of the board.
+    fn get_board_distribution(&self, board_id: String) -> Option<BoardDistribution>;
+}
+
+pub trait ProfileTransfer {
+    // Offer the profile of the caller (`predecessor_id`) to `new_account_id`, replacing any pending offer.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`.
+    // * The new account should not have a profile.
+    fn start_profile_transfer(&mut self, new_account_id: AccountId);
+
+    fn cancel_profile_transfer(&mut self);
+
+    // Move the profile offered by `old_account_id` to the caller (`predecessor_id`):
+    // characters, scores, score histories, reward time, submission limits, identity, attributes, display name, avatar, equipped item, friends, guild, region and settings.
+    // Requirements:
+    // * The old account should have no provisional score, score under review, committed score nor open game session.
+    fn accept_profile_transfer(&mut self, old_account_id: AccountId);
+
+    // Account the profile of the given account is offered to.
+    fn get_profile_transfer(&self, account_id: AccountId) -> Option<AccountId>;
+}
+
+pub trait LinkedAccounts {
+    // Propose to link `linked_account_id` to the profile of the caller (`predecessor_id`).
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`, and not be linked itself.
+    fn propose_link(&mut self, linked_account_id: AccountId);
+
+    // Link the caller (`predecessor_id`) to the profile of `primary_account_id`, which proposed it.
+    // Linked accounts play, get rewards and are ranked as their primary account.
+    // Requirements:
+    // * The caller should not have a profile or linked accounts.
+    // * A profile can not have more than 3 linked accounts.
+    fn confirm_link(&mut self, primary_account_id: AccountId);
+
+    // Unlink `account_id` from its primary account, called by either of them.
+    fn unlink(&mut self, account_id: AccountId);
+
+    // Account the given account plays as, itself when it is not linked.
+    fn get_primary_account(&self, account_id: AccountId) -> AccountId;
+
+    fn get_linked_accounts(&self, account_id: AccountId) -> Vec<AccountId>;
+}
+
+pub trait Friends {
+    // Ask `account_id` to be a friend of the caller (`predecessor_id`), accepted at once if it asked too.
+    // Requirements:
+    // * Both accounts should have a profile, see `grant_access`.
+    // * A player can not have more than 50 friends nor 50 pending requests.
+    fn send_friend_request(&mut self, account_id: AccountId);
+
+    // Accept the request sent by `account_id` to the caller.
+    fn accept_friend_request(&mut self, account_id: AccountId);
+
+    fn decline_friend_request(&mut self, account_id: AccountId);
+
+    fn remove_friend(&mut self, account_id: AccountId);
+
+    fn get_friends(&self, account_id: AccountId) -> Vec<AccountId>;
+
+    // Accounts waiting for the given account to accept their request.
+    fn get_friend_requests(&self, account_id: AccountId) -> Vec<AccountId>;
+
+    // Characters of the given account and its friends ordered like `get_top_players`.
+    // Views can not tell who is asking, so only the accounts whose rank is public are shown.
+    fn get_friends_leaderboard(&self, account_id: AccountId, from: u64, limit: u64) -> Vec<LeaderBoardEntry>;
+
+    // Same as `get_friends_leaderboard` for the caller (`predecessor_id`), with its own characters
+    // and the friends sharing their rank with friends. It should be called as a transaction.
+    fn get_friends_leaderboard_as_caller(&mut self, from: u64, limit: u64) -> Vec<LeaderBoardEntry>;
+}
+
+pub trait Guilds {
+    // Create a guild founded by the caller (`predecessor_id`) and return its id.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`, and not be in a guild.
+    // * The attached deposit should be at least the guild deposit, it is refunded to the caller when the guild is disbanded.
+    // * Guild names follow the rules of display names and are unique.
+    fn create_guild(&mut self, name: String) -> u64;
+
+    // Invite `account_id` to the guild of the caller, only officers can invite.
+    fn invite_to_guild(&mut self, account_id: AccountId);
+
+    fn accept_guild_invite(&mut self, guild_id: u64);
+
+    // Ask to join the given guild, the request is accepted by one of its officers.
+    fn request_to_join_guild(&mut self, guild_id: u64);
+
+    fn accept_join_request(&mut self, account_id: AccountId);
+
+    // Leave the guild of the caller. The founder hands over the guild to the oldest officer, or member,
+    // and the last member disbands it.
+    fn leave_guild(&mut self);
+
+    // Remove a member from the guild of the caller. Officers can kick members, the founder can kick officers too.
+    fn kick_from_guild(&mut self, account_id: AccountId);
+
+    // Promote a member to officer or demote an officer, only the founder can do it.
+    fn set_guild_officer(&mut self, account_id: AccountId, is_officer: bool);
+
+    fn get_guild(&self, guild_id: u64) -> Option<Guild>;
+
+    fn get_guild_of(&self, account_id: AccountId) -> Option<u64>;
+
+    // Guilds ordered by the aggregate of the best scores of their members, see `set_guild_rules`.
+    fn get_guild_leaderboard(&self, from: u64, limit: u64) -> Vec<GuildEntry>;
+}
+
+pub trait GuildTreasury {
+    // Set the share of the rewards of the members going to the treasury of the guild, and the share
+    // of the members whose approval is needed to spend it, both in percent. Only the founder can set them.
+    fn set_guild_treasury_rules(&mut self, tithe_percent: u8, quorum_percent: u8);
+
+    // Propose to give `amount` reward points of the treasury to `receiver_id`, only officers can propose.
+    fn propose_treasury_spend(&mut self, receiver_id: AccountId, amount: u64, description: String) -> u64;
+
+    // Vote on a proposal of the guild of the caller (`predecessor_id`).
+    // The spending is done once the quorum approved it, and rejected once the quorum can not be reached.
+    // Reward points are not kept on chain, like the rewards of `get_reward` the game credits the receiver
+    // from the `guild_treasury_debit` event. The last member of a disbanded guild receives what is left.
+    fn vote_treasury_spend(&mut self, proposal_id: u64, approve: bool);
+
+    fn get_treasury_proposal(&self, proposal_id: u64) -> Option<TreasuryProposal>;
+}
+
+pub trait Regions {
+    // Set the region of the caller (`predecessor_id`) from its ISO 3166-1 alpha-2 code, e.g. "VN".
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`.
+    // * The region can be set at most twice per season.
+    fn set_region(&mut self, region: String);
+
+    fn get_region(&self, account_id: AccountId) -> Option<String>;
+
+    // Characters of the players of the region ordered like `get_top_players`.
+    fn get_top_players_by_region(&self, region: String, from: u64, limit: u64) -> Vec<LeaderBoardEntry>;
+
+    // Regions ordered by the total, or the average, of the best scores of their players.
+    fn get_region_leaderboard(&self, ranking: RegionRanking, from: u64, limit: u64) -> Vec<RegionEntry>;
+}
+
+pub trait GameSessions {
+    // Start a play of the caller (`predecessor_id`) on the given board and return its session id.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`, or be linked to one.
+    // * The attached deposit should be at least the session deposit, it is refunded when the session ends.
+    fn start_session(&mut self, board_id: String) -> u64;
+
+    // Submit the score of the session attested by a game server, like `submit_score` on its board.
+    // Requirements:
+    // * Only the player of the session can submit, once.
+    // * The session should have lasted between the minimum and the maximum duration, see `set_session_rules`.
+    // * The attestation should be for the player and the board of the session, and valid like in `submit_attested_score`.
+    fn submit_session_score(&mut self, session_id: u64, attestation: ScoreAttestation, character: Option<String>, signature: Base64VecU8, replay: Option<ReplayProof>) -> bool;
+
+    // End the session and refund its deposit, sessions ended without a score count as abandoned.
+    fn end_session(&mut self, session_id: u64);
+
+    // End up to `limit` sessions past their maximum duration, returns how many were ended.
+    // Anyone can call it, the deposits go back to the players.
+    fn clean_expired_sessions(&mut self, limit: u64) -> u64;
+
+    fn get_session(&self, session_id: u64) -> Option<GameSession>;
+
+    fn get_session_stats(&self, board_id: String) -> SessionStats;
+}
+
+pub trait Characters {
+    // Add a character to the profile of the caller (`predecessor_id`), it starts with a score of 0.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`.
+    // * A profile can not have more than 5 characters.
+    // * Character names follow the rules of display names and are unique within the profile.
+    fn create_character(&mut self, name: String);
+
+    fn rename_character(&mut self, name: String, new_name: String);
+
+    // Delete a character of the caller.
+    // The default character, named after the account, can not be renamed nor deleted.
+    fn delete_character(&mut self, name: String);
+
+    fn get_characters(&self, account_id: AccountId) -> Vec<(String, Profile)>;
+}
+
+pub trait PersonalInfo {
+    // Set the first name, last name and age of the given account, missing values are left unchanged.
+    // Names are NFC normalized with their whitespaces collapsed.
+    // Requirements:
+    // * The caller of the function (`predecessor_id`) should be the account itself or one of its editors.
+    // * The account should have a profile, see `grant_access`.
+    fn set_info(&mut self, account_id: AccountId, age: Option<u8>, f_name: Option<String>, l_name: Option<String>) -> bool;
+
+    // Remove the given fields from the info of the given account, same requirements as `set_info`.
+    fn clear_info(&mut self, account_id: AccountId, fields: Vec<InfoField>) -> bool;
+
+    // Allow `editor_id` to edit the info of the caller (`predecessor_id`).
+    fn add_profile_editor(&mut self, editor_id: AccountId);
+
+    fn remove_profile_editor(&mut self, editor_id: AccountId);
+
+    fn get_profile_editors(&self, account_id: AccountId) -> Vec<AccountId>;
+
+    // Set attributes of the given account, same requirements as `set_info`.
+    // * Each key should be in the attribute schema and its value should match the type and size of the key.
+    fn set_attributes(&mut self, account_id: AccountId, attributes: HashMap<String, String>) -> bool;
+
+    fn remove_attributes(&mut self, account_id: AccountId, keys: Vec<String>) -> bool;
+
+    // Identity, attributes and leaderboard stats of the given account.
+    // The identity and attributes are left empty unless the account made its info public.
+    fn get_info(&self, account_id: AccountId) -> Option<ProfileInfo>;
+
+    fn introduce(&self, account_id: AccountId) -> Option<(String, String, String)>;
+}
+
+pub trait Privacy {
+    // Set who can see the info and the rank of the caller (`predecessor_id`).
+    // Players whose rank is not public are left out of `get_top_players`.
+    fn set_privacy(&mut self, settings: PrivacySettings);
+
+    fn get_privacy(&self, account_id: AccountId) -> PrivacySettings;
+
+    // Same as `get_info`, as seen by the caller (`predecessor_id`).
+    // Views can not tell who is asking, so it should be called as a transaction.
+    fn get_info_as_caller(&mut self, account_id: AccountId) -> Option<ProfileInfo>;
+
+    // Rank of the best profile of the caller (`predecessor_id`) among all players, whatever their privacy.
+    // It should be called as a transaction, like `get_info_as_caller`.
+    fn get_own_rank(&mut self) -> Option<u64>;
+}
+
+pub trait DisplayNames {
+    // Set the display name of the caller (`predecessor_id`), the first one is free.
+    // Names differing only by case or by look-alike characters are the same name.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`.
+    // * The name should have 3 to 32 letters, digits, spaces, `_` or `-`, and not be taken or reserved.
+    // * Renames are allowed once the cooldown is over, or before with a deposit of the rename fee.
+    fn set_display_name(&mut self, name: String);
+
+    // Keep the given names from being taken, only the contract owner can reserve names.
+    fn reserve_display_names(&mut self, names: Vec<String>);
+
+    fn release_display_name(&mut self, name: String);
+
+    // Give a reserved name to the given account, only the contract owner can assign names.
+    fn assign_display_name(&mut self, account_id: AccountId, name: String);
+
+    fn get_display_name(&self, account_id: AccountId) -> Option<String>;
+
+    fn get_account_by_display_name(&self, name: String) -> Option<AccountId>;
+}
+
+pub trait SeasonPrizes {
+    // Freeze the current standings into a new season and return its id.
+    // Players hiding their rank, see `set_privacy`, are left out of the season.
+    // Requirements:
+    // * Only the contract owner can finalize a season.
+    // * The attached deposit becomes the prize pool of the season, there should be a ranked player to win it.
+    // With fewer winners than ranks in `SEASON_PRIZE_TIERS`, the whole pool is shared by the winners in proportion to their tier.
+    fn finalize_season(&mut self) -> u64;
+
+    // Send the trophies and prizes of the given season, at most `limit` winners per call.
+    // Winners already paid (or being paid) are skipped, so the call can be retried safely.
+    // Returns the number of winners handled by this call.
+    fn distribute_season_prizes(&mut self, season_id: u64, limit: u64) -> u64;
+
+    // Frozen standings of the given season as (username, score), ordered by rank.
+    // Like the winners, players hiding their rank since the season was finalized are not shown.
+    fn get_season_standings(&self, season_id: u64, from: u64, limit: u64) -> Vec<(String, i32)>;
+
+    // Winners of the given season with their trophy and payout status.
+    fn get_season_winners(&self, season_id: u64) -> Vec<SeasonWinner>;
+}
+
+pub trait NonFungibleTokenReceiver {
+    // Equip the item sent by `previous_owner_id` through `nft_transfer_call` on the item contract.
+    // Returns `true` when the item can not be equipped and should go back to its owner.
+    fn nft_on_transfer(&mut self, sender_id: AccountId, previous_owner_id: AccountId, token_id: u64, msg: String) -> PromiseOrValue<bool>;
+}
+
+pub trait EquippedItems {
+    // Send the item equipped by the caller (`predecessor_id`) back to them.
+    fn unequip_item(&mut self) -> Promise;
+
+    fn get_equipped_item(&self, account_id: AccountId) -> Option<EquippedItem>;
+
+    // Multiplier applied to the scores of the given account, in basis points.
+    fn get_score_multiplier(&self, account_id: AccountId) -> u32;
+}
+
+pub trait Avatars {
+    // Show the given NFT as the avatar of the caller (`predecessor_id`), once its owner is checked on the NFT contract.
+    // Requirements:
+    // * The caller should have a profile, see `grant_access`.
+    // * The caller should own the token.
+    // * The NFT contract should be allowed by the owner, see `add_avatar_contract`.
+    fn set_avatar(&mut self, nft_contract_id: AccountId, token_id: u64) -> Promise;
+
+    // Check the avatar of the given account is still owned by it, the avatar is removed otherwise.
+    // Anyone can call it, e.g. when the token is seen on a marketplace.
+    fn refresh_avatar(&mut self, account_id: AccountId) -> Promise;
+
+    fn clear_avatar(&mut self);
+
+    fn get_avatar(&self, account_id: AccountId) -> Option<Avatar>;
+}
+
+#[ext_contract(ext_avatar_nft)]
+pub trait AvatarContract {
+    fn get_token_owner(&self, token_id: u64) -> String;
+}
+
+#[ext_contract(ext_trophy)]
+pub trait TrophyContract {
+    fn mint_next(&mut self, owner_id: AccountId, metadata: TrophyMetadata) -> u64;
+}
+
+#[ext_contract(ext_items)]
+pub trait ItemContract {
+    fn get_token_metadata(&self, token_id: u64) -> Option<ItemMetadata>;
+    fn transfer(&mut self, new_owner_id: AccountId, token_id: u64);
+}
+
+#[ext_contract(ext_self)]
+pub trait ProfileStateCallbacks {
+    fn on_season_payout(&mut self, season_id: u64, winner_index: u64, is_trophy: bool) -> bool;
+    fn on_item_metadata(&mut self, account_id: AccountId, token_id: u64) -> bool;
+    fn on_item_unequipped(&mut self, account_id: AccountId, item: EquippedItem) -> bool;
+    fn on_avatar_owner(&mut self, account_id: AccountId, avatar: Avatar, is_new: bool) -> bool;
 }
 
 const REWARD_FREQUENCY: i64 = 3600 * 1000; // 1 hour = 3600 * 1000 miliseconds
@@ -40,12 +478,179 @@ const REWARD_RANGE:[(i32, i32); 5] = [
     (200, 200),
     (1000, 1000),
 ];
+// (last rank of the tier, trophy, share of the prize pool per player in basis points)
+const SEASON_PRIZE_TIERS: [(u64, &str, u128); 3] = [
+    (1, "gold", 3500),
+    (3, "silver", 1500),
+    (10, "bronze", 500),
+];
+const MAX_SEASON_PAYOUT_BATCH: u64 = 4;
+const GAS_FOR_TROPHY_MINT: Gas = 10_000_000_000_000;
+const GAS_FOR_SEASON_CALLBACK: Gas = 5_000_000_000_000;
+// one prize transfer and one trophy mint with their callbacks, including the receipts fees
+const GAS_FOR_SEASON_PAYOUT: Gas = 60_000_000_000_000;
+// score multipliers are in basis points, 10_000 keeps the score as is
+const MULTIPLIER_DENOMINATOR: u32 = 10_000;
+const GAS_FOR_ITEM_CALL: Gas = 10_000_000_000_000;
+const GAS_FOR_ITEM_CALLBACK: Gas = 10_000_000_000_000;
+const MAX_NAME_LENGTH: usize = 64; // in characters, after normalization
+const AGE_RANGE: (u8, u8) = (13, 120);
+const MAX_PROFILE_EDITORS: usize = 5;
+const MAX_CHARACTERS: u64 = 5;
+const MAX_LINKED_ACCOUNTS: usize = 3;
+const MAX_FRIENDS: usize = 50;
+const MAX_FRIEND_REQUESTS: usize = 50;
+const DEFAULT_GUILD_DEPOSIT: Balance = 1_000_000_000_000_000_000_000_000; // 1 NEAR
+const MAX_GUILD_MEMBERS: usize = 50;
+const MAX_GUILD_REQUESTS: usize = 50; // pending invites and join requests, each
+const MAX_GUILD_TITHE_PERCENT: u8 = 50;
+const DEFAULT_GUILD_QUORUM_PERCENT: u8 = 50;
+const TREASURY_PROPOSAL_DURATION: u64 = 7 * 24 * 3600 * 1_000_000_000; // 7 days in nanoseconds
+const MAX_PROPOSAL_DESCRIPTION_LENGTH: usize = 256;
+const MAX_REGION_CHANGES_PER_SEASON: u8 = 2;
+const DEFAULT_BOARD_ID: &str = "global";
+const MAX_BOARD_ID_LENGTH: usize = 32;
+const MAX_BOARDS_PER_CHARACTER: usize = 20;
+const DEFAULT_HISTORY_DEPTH: u32 = 20;
+const MAX_HISTORY_DEPTH: u32 = 100;
+const MAX_CID_LENGTH: usize = 128;
+const MAX_DISPUTE_REASON_LENGTH: usize = 256;
+const DEFAULT_CHALLENGE_WINDOW: u64 = 24 * 3600 * 1_000_000_000; // 1 day in nanoseconds
+const DEFAULT_DISPUTE_BOND: Balance = 1_000_000_000_000_000_000_000_000; // 1 NEAR
+const HISTOGRAM_BUCKETS: usize = 32;
+const DAY: u64 = 24 * 3600 * 1_000_000_000; // in nanoseconds
+const DEFAULT_SESSION_DEPOSIT: Balance = 10_000_000_000_000_000_000_000; // 0.01 NEAR
+const DEFAULT_SESSION_DURATION: (u64, u64) = (10 * 1_000_000_000, 3600 * 1_000_000_000); // 10 seconds to 1 hour in nanoseconds
+// ISO 3166-1 alpha-2 codes, sorted for `binary_search`
+const REGION_CODES: [&str; 249] = [
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+// NEP-297 events
+const EVENT_STANDARD: &str = "leaderboard";
+const EVENT_VERSION: &str = "1.0.0";
+const GAS_FOR_AVATAR_CALL: Gas = 10_000_000_000_000;
+const GAS_FOR_AVATAR_CALLBACK: Gas = 10_000_000_000_000;
+const DISPLAY_NAME_LENGTH: (usize, usize) = (3, 32); // in characters, after normalization
+const MAX_ATTRIBUTE_KEY_LENGTH: usize = 32;
+const MAX_ATTRIBUTE_LENGTH: u32 = 1024; // in characters
 type AccountIdHash = Vec<u8>;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct ProfileState {
-    state: UnorderedMap<AccountIdHash, UnorderedMap<String, Profile>>
+    state: UnorderedMap<AccountIdHash, UnorderedMap<String, Profile>>,
+    owner_id: AccountId,
+    trophy_contract_id: Option<AccountId>,
+    seasons: Vector<Season>,
+    item_contract_id: Option<AccountId>,
+    // (min, max) of the multiplier of equipped items, in basis points
+    multiplier_bounds: (u32, u32),
+    equipped_items: UnorderedMap<AccountIdHash, EquippedItem>,
+    identities: UnorderedMap<AccountIdHash, Identity>,
+    // identities of a `profile_v2` deployment not moved to `identities` yet, see `migrate_identities`
+    legacy_identities: Option<UnorderedMap<AccountIdHash, LegacyIdentity>>,
+    profile_editors: UnorderedMap<AccountIdHash, Vec<AccountId>>,
+    display_names: UnorderedMap<AccountIdHash, DisplayName>,
+    // owners of the display names by name key, see `display_name_key`
+    name_owners: UnorderedMap<String, AccountId>,
+    reserved_names: UnorderedSet<String>,
+    rename_fee: Balance,
+    rename_cooldown: u64, // in nanoseconds
+    attribute_schema: UnorderedMap<String, AttributeSchema>,
+    attributes: UnorderedMap<AccountIdHash, HashMap<String, String>>,
+    avatars: UnorderedMap<AccountIdHash, Avatar>,
+    // NFT contracts whose tokens can be avatars, set by the owner
+    avatar_contracts: UnorderedSet<AccountId>,
+    privacy: UnorderedMap<AccountIdHash, PrivacySettings>,
+    // pending transfers by the hash of the account giving its profile
+    profile_transfers: UnorderedMap<AccountIdHash, AccountId>,
+    // primary account of the linked accounts, and pending proposals, by the hash of the linked account
+    primary_accounts: UnorderedMap<AccountIdHash, AccountId>,
+    link_proposals: UnorderedMap<AccountIdHash, AccountId>,
+    linked_accounts: UnorderedMap<AccountIdHash, Vec<AccountId>>,
+    friends: UnorderedMap<AccountIdHash, Vec<AccountId>>,
+    // senders of the pending requests by the hash of the account they are sent to
+    friend_requests: UnorderedMap<AccountIdHash, Vec<AccountId>>,
+    guilds: UnorderedMap<u64, Guild>,
+    next_guild_id: u64,
+    // guild ids by name key, see `display_name_key`
+    guild_names: UnorderedMap<String, u64>,
+    member_guilds: UnorderedMap<AccountIdHash, u64>,
+    guild_deposit: Balance,
+    guild_score_rule: GuildScoreRule,
+    treasury_proposals: UnorderedMap<u64, TreasuryProposal>,
+    next_proposal_id: u64,
+    player_regions: UnorderedMap<AccountIdHash, PlayerRegion>,
+    // characters of the players of each region, stored under "region-{code}"
+    region_ranks: UnorderedMap<String, RankedIndex>,
+    board_ranks: UnorderedMap<String, RankedIndex>,
+    region_stats: UnorderedMap<String, RegionEntry>,
+    // by `history_key`
+    score_histories: UnorderedMap<Vec<u8>, ProfileHistory>,
+    history_depth: u32,
+    sessions: UnorderedMap<u64, GameSession>,
+    next_session_id: u64,
+    // sessions before it are ended, see `clean_expired_sessions`
+    oldest_session_id: u64,
+    session_stats: UnorderedMap<String, SessionStats>,
+    session_deposit: Balance,
+    // (min, max) in nanoseconds
+    session_duration: (u64, u64),
+    server_keys: UnorderedSet<Base58PublicKey>,
+    // expiry of the used attestations by hash of their account and nonce
+    used_nonces: LookupMap<Vec<u8>, u64>,
+    // the same by expiry, the oldest first
+    nonce_expiries: TreeMap<(u64, Vec<u8>), ()>,
+    // boards which only accept committed scores
+    commit_reveal: UnorderedMap<String, CommitRevealRules>,
+    // by hash of the account, character and board
+    score_commits: UnorderedMap<Vec<u8>, ScoreCommit>,
+    // keys of the commits by reveal deadline, the oldest first
+    commit_expiries: TreeMap<(u64, Vec<u8>), ()>,
+    moderators: UnorderedSet<AccountId>,
+    // game servers submitting scores for the players
+    reporters: UnorderedSet<AccountId>,
+    challenge_rules: ChallengeRules,
+    provisional_scores: UnorderedMap<u64, ProvisionalScore>,
+    next_provisional_id: u64,
+    // id of the provisional score of each character on a board, by `commit_key`
+    pending_provisionals: LookupMap<Vec<u8>, u64>,
+    board_rules: UnorderedMap<String, BoardRules>,
+    // by account hash and board id
+    submission_windows: UnorderedMap<Vec<u8>, SubmissionWindow>,
+    // by board id, and by account hash and board id
+    rejection_counts: UnorderedMap<Vec<u8>, RejectionCounts>,
+    board_distributions: UnorderedMap<String, BoardDistribution>,
+    outlier_rules: Option<OutlierRules>,
+    pending_reviews: UnorderedMap<u64, PendingReview>,
+    next_review_id: u64,
+}
+
+/// State layout of the leaderboard before profiles had an identity
+#[derive(BorshDeserialize, BorshSerialize)]
+struct LegacyLeaderBoardState {
+    state: UnorderedMap<AccountIdHash, UnorderedMap<String, Profile>>,
+}
+
+/// State layout of the `profile_v2` contract
+#[derive(BorshDeserialize, BorshSerialize)]
+struct LegacyProfileV2State {
+    state: UnorderedMap<AccountIdHash, LegacyIdentity>,
 }
 
 impl Default for ProfileState {
@@ -59,512 +664,5793 @@ impl ProfileState {
     #[init]
     pub fn
     new() -> Self {
-        Self {
-            state: UnorderedMap::new(b"grant".to_vec())
-        }
+        Self::with_profiles(UnorderedMap::new(b"grant".to_vec()), None)
     }
 
+    /// Upgrades a leaderboard deployed before identities, the profiles are kept as they are
+    #[init(ignore_state)]
+    #[private]
     pub fn
-    get_list_user(&self, account_id: AccountId) -> Vec<Profile> {
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        let map = self.state.get(&escrow_hash).unwrap();
-        let keys = map.keys();
-        let mut vec:Vec<Profile> = vec![];
-        for key in keys {
-            vec.push(map.get(&key).unwrap())
-        }
-        return vec;
+    migrate() -> Self {
+        let old: LegacyLeaderBoardState = env::state_read().expect("Old state doesn't exist");
+        Self::with_profiles(old.state, None)
     }
 
-    fn
-    flat_users(&self) -> Vec<(String, Profile)> {
-        let mut vec: Vec<(String, Profile)> = vec![];
-        for value in self.state.values() {
-            vec.append(&mut value.to_vec())
+    /// Turns a `profile_v2` deployment into a leaderboard, its profiles become identities.
+    /// The identities are moved by `migrate_identities` and stay readable in the meantime.
+    #[init(ignore_state)]
+    #[private]
+    pub fn
+    migrate_from_profile_v2() -> Self {
+        let old: LegacyProfileV2State = env::state_read().expect("Old state doesn't exist");
+        // `profile_v2` already stores its profiles under "grant"
+        Self::with_profiles(UnorderedMap::new(b"profiles".to_vec()), Some(old.state))
+    }
+
+    /// Moves up to `limit` identities of a `profile_v2` deployment, returns how many are left
+    pub fn
+    migrate_identities(&mut self, limit: u64) -> u64 {
+        self.only_owner();
+        let mut legacy = match self.legacy_identities.take() {
+            Some(legacy) => legacy,
+            None => return 0
         };
+        let keys: Vec<AccountIdHash> = legacy.keys().take(limit as usize).collect();
+        for key in keys {
+            let identity = legacy.remove(&key).unwrap();
+            if self.identities.get(&key).is_none() {
+                self.identities.insert(&key, &identity.into());
+            }
+        }
 
-        return vec;
+        let left = legacy.len();
+        if left > 0 {
+            self.legacy_identities = Some(legacy);
+        }
+        env::log(format!("{} identities left to migrate", left).as_bytes());
+        left
     }
-}
 
-#[near_bindgen]
-impl NEP4 for ProfileState {
-    fn grant_access(&mut self, account_id: AccountId) {
-        let account_ref: &str = account_id.as_ref();
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        if self.check_access(account_ref.to_string()) {
-            env::log("existed!".as_bytes());
-            println!("existed!");
-        } else {
-            let new_profile = &Profile::new(String::from(account_ref));
-            let mut new_map = UnorderedMap::new(account_ref.as_bytes());
-            new_map.insert(&String::from(account_ref), new_profile);
-            self.state.insert(&escrow_hash, &new_map);
-            env::log("granted!".as_bytes());
-            println!("granted!");
+    /// Imports a batch of identities read from a `profile_v2` deployment into this leaderboard,
+    /// identities already set here are kept. Returns how many were imported.
+    pub fn
+    import_identities(&mut self, identities: Vec<(AccountId, LegacyIdentity)>) -> u64 {
+        self.only_owner();
+        let mut imported = 0;
+        for (account_id, identity) in identities {
+            let key = env::sha256(account_id.as_bytes());
+            if self.identities.get(&key).is_none() {
+                self.identities.insert(&key, &identity.into());
+                imported += 1;
+            }
         }
+
+        env::log(format!("{} identities imported", imported).as_bytes());
+        imported
     }
 
-    fn revoke_access(&mut self, account_id: AccountId) {
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        if self.check_access(account_id) {
-            self.state.remove(&escrow_hash);
-            env::log("revoked!".as_bytes());
-            println!("revoked!");
-        } else {
-            env::log("not found to revoke!".as_bytes());
-            println!("not found to revoke!");
+    fn
+    with_profiles(state: UnorderedMap<AccountIdHash, UnorderedMap<String, Profile>>, legacy_identities: Option<UnorderedMap<AccountIdHash, LegacyIdentity>>) -> Self {
+        Self {
+            state,
+            owner_id: env::predecessor_account_id(),
+            trophy_contract_id: None,
+            seasons: Vector::new(b"seasons".to_vec()),
+            item_contract_id: None,
+            multiplier_bounds: (MULTIPLIER_DENOMINATOR, MULTIPLIER_DENOMINATOR),
+            equipped_items: UnorderedMap::new(b"equipped".to_vec()),
+            identities: UnorderedMap::new(b"identities".to_vec()),
+            legacy_identities,
+            profile_editors: UnorderedMap::new(b"editors".to_vec()),
+            display_names: UnorderedMap::new(b"display-names".to_vec()),
+            name_owners: UnorderedMap::new(b"name-owners".to_vec()),
+            reserved_names: UnorderedSet::new(b"reserved-names".to_vec()),
+            rename_fee: 0,
+            rename_cooldown: 0,
+            attribute_schema: UnorderedMap::new(b"attribute-schema".to_vec()),
+            attributes: UnorderedMap::new(b"attributes".to_vec()),
+            avatars: UnorderedMap::new(b"avatars".to_vec()),
+            avatar_contracts: UnorderedSet::new(b"avatar-contracts".to_vec()),
+            privacy: UnorderedMap::new(b"privacy".to_vec()),
+            profile_transfers: UnorderedMap::new(b"profile-transfers".to_vec()),
+            primary_accounts: UnorderedMap::new(b"primary-accounts".to_vec()),
+            link_proposals: UnorderedMap::new(b"link-proposals".to_vec()),
+            linked_accounts: UnorderedMap::new(b"linked-accounts".to_vec()),
+            friends: UnorderedMap::new(b"friends".to_vec()),
+            friend_requests: UnorderedMap::new(b"friend-requests".to_vec()),
+            guilds: UnorderedMap::new(b"guilds".to_vec()),
+            next_guild_id: 0,
+            guild_names: UnorderedMap::new(b"guild-names".to_vec()),
+            member_guilds: UnorderedMap::new(b"member-guilds".to_vec()),
+            guild_deposit: DEFAULT_GUILD_DEPOSIT,
+            guild_score_rule: GuildScoreRule::Sum,
+            treasury_proposals: UnorderedMap::new(b"treasury-proposals".to_vec()),
+            next_proposal_id: 0,
+            player_regions: UnorderedMap::new(b"player-regions".to_vec()),
+            region_ranks: UnorderedMap::new(b"region-ranks".to_vec()),
+            board_ranks: UnorderedMap::new(b"board-ranks".to_vec()),
+            region_stats: UnorderedMap::new(b"region-stats".to_vec()),
+            score_histories: UnorderedMap::new(b"score-histories".to_vec()),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            sessions: UnorderedMap::new(b"sessions".to_vec()),
+            next_session_id: 0,
+            oldest_session_id: 0,
+            session_stats: UnorderedMap::new(b"session-stats".to_vec()),
+            session_deposit: DEFAULT_SESSION_DEPOSIT,
+            session_duration: DEFAULT_SESSION_DURATION,
+            server_keys: UnorderedSet::new(b"server-keys".to_vec()),
+            used_nonces: LookupMap::new(b"used-nonces".to_vec()),
+            nonce_expiries: TreeMap::new(b"nonce-expiries".to_vec()),
+            commit_reveal: UnorderedMap::new(b"commit-reveal".to_vec()),
+            score_commits: UnorderedMap::new(b"score-commits".to_vec()),
+            commit_expiries: TreeMap::new(b"commit-expiries".to_vec()),
+            moderators: UnorderedSet::new(b"moderators".to_vec()),
+            reporters: UnorderedSet::new(b"reporters".to_vec()),
+            challenge_rules: ChallengeRules { top: 0, window: DEFAULT_CHALLENGE_WINDOW, bond: U128(DEFAULT_DISPUTE_BOND) },
+            provisional_scores: UnorderedMap::new(b"provisional-scores".to_vec()),
+            next_provisional_id: 0,
+            pending_provisionals: LookupMap::new(b"pending-provisionals".to_vec()),
+            board_rules: UnorderedMap::new(b"board-rules".to_vec()),
+            submission_windows: UnorderedMap::new(b"submission-windows".to_vec()),
+            rejection_counts: UnorderedMap::new(b"rejection-counts".to_vec()),
+            board_distributions: UnorderedMap::new(b"board-distributions".to_vec()),
+            outlier_rules: None,
+            pending_reviews: UnorderedMap::new(b"pending-reviews".to_vec()),
+            next_review_id: 0,
         }
     }
 
-    fn check_access(&self, account_id: AccountId) -> bool {
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        return match self.state.get(&escrow_hash) {
-            Some(_) => {
-                true
-            }
-            _ => {
-                false
-            }
-        };
+    /// Deposit to start a session, and its minimum and maximum duration in nanoseconds
+    pub fn
+    set_session_rules(&mut self, deposit: U128, min_duration: u64, max_duration: u64) {
+        self.only_owner();
+        assert!(min_duration <= max_duration, "Minimum duration is above the maximum.");
+        self.session_deposit = deposit.0;
+        self.session_duration = (min_duration, max_duration);
     }
-}
 
-#[near_bindgen]
-impl LeaderBoard for ProfileState {
-    fn get_top_players(&self) -> Vec<(String, Profile)> {
-        let mut flat_users = self.flat_users();
-        return mut_to_sorted_vec(&mut flat_users, false);
+    pub fn
+    get_session_rules(&self) -> (U128, u64, u64) {
+        (U128(self.session_deposit), self.session_duration.0, self.session_duration.1)
     }
 
-    fn get_reward(&mut self, account_id: AccountId) -> i32 {
-        let account_ref: &str = account_id.as_ref();
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        if !self.check_access(account_ref.to_string()) || self.is_recently_rewarded(account_ref.to_string()){
-            return 0;
-        }
+    /// Trusts the ed25519 key of a game server to sign scores, see `submit_attested_score`.
+    /// Keys are rotated by adding the new one before removing the old one.
+    /// While a key is registered, scores can not be submitted without a signature.
+    pub fn
+    add_server_key(&mut self, public_key: Base58PublicKey) {
+        self.only_owner();
+        assert!(public_key.0.len() == 33 && public_key.0[0] == 0, "Only ed25519 keys are supported.");
+        ed25519_dalek::PublicKey::from_bytes(&public_key.0[1..]).expect("Invalid ed25519 key.");
+        self.server_keys.insert(&public_key);
+    }
 
-        let map_users = self.state.get(&escrow_hash);
-        let top_users = get_sorted_profiles_by_score(&map_users.unwrap(), true);
-        let highest_score = top_users[0].1.score;
-        let mut reward = 0;
-        for max in REWARD_RANGE {
-          if (highest_score as i32) < max.0 {
-              reward = max.1;
-              break;
-          }
-        };
+    pub fn
+    remove_server_key(&mut self, public_key: Base58PublicKey) {
+        self.only_owner();
+        assert!(self.server_keys.remove(&public_key), "Unknown server key.");
+    }
 
-        let reward_time = Utc::now().timestamp_millis();
-        let clone_map = self.state.get(&escrow_hash).unwrap();
-        let mut update_map = UnorderedMap::new(account_id.as_bytes());
-        if reward > 0 {
-            for key in clone_map.keys() {
-                let mut update_profile = clone_map.get(&key).unwrap().clone();
-                update_profile.rewarded(reward_time);
-                update_map.insert(&key, &update_profile);
-            }
+    pub fn
+    get_server_keys(&self) -> Vec<Base58PublicKey> {
+        self.server_keys.to_vec()
+    }
 
-            self.state.insert(&escrow_hash, &update_map);
+    /// Turns the board into a commit-reveal board until its reveal deadline, or back into a regular one with `None`.
+    /// Setting new deadlines starts a new round, the commits of the previous one can no longer be revealed.
+    pub fn
+    set_commit_reveal(&mut self, board_id: String, rules: Option<CommitRevealRules>) {
+        self.only_owner();
+        match rules {
+            Some(rules) => {
+                assert!(rules.commit_end.0 < rules.reveal_end.0, "The commit deadline should be before the reveal deadline.");
+                self.commit_reveal.insert(&board_id, &rules);
+            }
+            None => {
+                self.commit_reveal.remove(&board_id);
+            }
         }
+    }
 
-        return reward;
+    pub fn
+    get_commit_reveal(&self, board_id: String) -> Option<CommitRevealRules> {
+        self.commit_reveal.get(&board_id)
     }
 
-    fn is_recently_rewarded(&self, account_id: AccountId) -> bool {
-        let account_ref: &str = account_id.as_ref();
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        if !self.check_access(account_ref.to_string()) {
-            return true;
+    pub fn
+    add_moderator(&mut self, account_id: AccountId) {
+        self.only_owner();
+        self.moderators.insert(&account_id);
+    }
+
+    pub fn
+    remove_moderator(&mut self, account_id: AccountId) {
+        self.only_owner();
+        assert!(self.moderators.remove(&account_id), "Unknown moderator.");
+    }
+
+    pub fn
+    get_moderators(&self) -> Vec<AccountId> {
+        self.moderators.to_vec()
+    }
+
+    /// Reporters can submit scores for any player with `submit_score` and `save_new_score`
+    pub fn
+    add_reporter(&mut self, account_id: AccountId) {
+        self.only_owner();
+        self.reporters.insert(&account_id);
+    }
+
+    pub fn
+    remove_reporter(&mut self, account_id: AccountId) {
+        self.only_owner();
+        assert!(self.reporters.remove(&account_id), "Unknown reporter.");
+    }
+
+    pub fn
+    get_reporters(&self) -> Vec<AccountId> {
+        self.reporters.to_vec()
+    }
+
+    /// New best scores in the `top` of the ranking are provisional during the `window` in nanoseconds,
+    /// disputing one costs the `bond`. A `top` of 0 ranks every score right away.
+    pub fn
+    set_challenge_rules(&mut self, rules: ChallengeRules) {
+        self.only_owner();
+        self.challenge_rules = rules;
+    }
+
+    pub fn
+    get_challenge_rules(&self) -> ChallengeRules {
+        self.challenge_rules.clone()
+    }
+
+    /// Sanity rules of the scores submitted to the board, `None` accepts any score.
+    /// Only the default board ranks the profile, its rules also apply to `save_new_score`.
+    pub fn
+    set_board_rules(&mut self, board_id: String, rules: Option<BoardRules>) {
+        self.only_owner();
+        match rules {
+            Some(rules) => {
+                if let (Some(min_score), Some(max_score)) = (rules.min_score, rules.max_score) {
+                    assert!(min_score <= max_score, "Minimum score is above the maximum.");
+                }
+                assert!(rules.max_improvement.is_none_or(|max_improvement| max_improvement >= 0), "Maximum improvement should not be negative.");
+                self.board_rules.insert(&board_id, &rules);
+            }
+            None => {
+                self.board_rules.remove(&board_id);
+            }
         }
+    }
 
-        let user = self.state.get(&escrow_hash).unwrap().values().next().unwrap();
-        return Utc::now().timestamp_millis() - user.hourly_reward_at < REWARD_FREQUENCY;
+    pub fn
+    get_board_rules(&self, board_id: String) -> Option<BoardRules> {
+        self.board_rules.get(&board_id)
     }
 
-    fn save_new_score(&mut self, account_id: AccountId, score: i32) -> bool {
-        let account_ref: &str = account_id.as_ref();
-        let escrow_hash = env::sha256(account_id.as_bytes());
-        if !self.check_access(account_ref.to_string()) {
-            return false;
-        };
-        let mut map_profile = self.state.get(&escrow_hash).unwrap();
-        let user = map_profile.get(&account_ref.to_string());
-        if user.is_none() {
-            return false;
-        };
-        let mut profile = user.unwrap();
-        if profile.get_score() < score {
-            profile.set_score(score);
-            map_profile.insert(&account_ref.to_string(), &profile);
-            self.state.insert(&escrow_hash, &map_profile);
-            return true;
+    /// Rejected submissions of the board, or only those of the given account.
+    /// Views can not tell who is asking, so it should be called as a transaction.
+    pub fn
+    get_rejection_counts(&mut self, board_id: String, account_id: Option<AccountId>) -> RejectionCounts {
+        self.only_moderator();
+        let key = match account_id {
+            Some(account_id) => board_key(&env::sha256(self.get_primary_account(account_id).as_bytes()), &board_id),
+            None => board_id.into_bytes()
         };
+        self.rejection_counts.get(&key).unwrap_or_default()
+    }
 
-        return false;
+    /// Holds suspicious scores for review, see `approve_score`, or ranks every score with `None`
+    pub fn
+    set_outlier_rules(&mut self, rules: Option<OutlierRules>) {
+        self.only_owner();
+        if let Some(rules) = &rules {
+            assert!(rules.max_deviations > 0.0, "Maximum deviations should be positive.");
+        }
+        self.outlier_rules = rules;
     }
-}
 
-#[derive(Default, BorshDeserialize, BorshSerialize)]
-pub struct Profile {
-    username: String,
-    score: i32,
-    hourly_reward_at: i64
-}
+    pub fn
+    get_outlier_rules(&self) -> Option<OutlierRules> {
+        self.outlier_rules.clone()
+    }
 
-impl Profile {
-    #[init]
+    /// Number of recent submissions kept for each character, older ones are dropped on their next submission
     pub fn
-    new(account_id: String) -> Self {
-        Self {
-            score: 0,
-            username: account_id,
-            hourly_reward_at: 0,
+    set_history_depth(&mut self, depth: u32) {
+        self.only_owner();
+        assert!(0 < depth && depth <= MAX_HISTORY_DEPTH, "History depth should be between 1 and {}.", MAX_HISTORY_DEPTH);
+        self.history_depth = depth;
+    }
+
+    pub fn
+    get_history_depth(&self) -> u32 {
+        self.history_depth
+    }
+
+    /// Deposit to create a guild and how the scores of its members are aggregated
+    pub fn
+    set_guild_rules(&mut self, deposit: U128, score_rule: GuildScoreRule) {
+        self.only_owner();
+        if let GuildScoreRule::AverageOfTop(count) = score_rule {
+            assert!(count > 0, "The average should be of at least one member.");
         }
+        self.guild_deposit = deposit.0;
+        self.guild_score_rule = score_rule;
     }
 
     pub fn
-    set_score(&mut self, score: i32) {
-        self.score = score;
-        env::log(format!("score now is {}.", self.score).as_bytes());
+    get_guild_rules(&self) -> (U128, GuildScoreRule) {
+        (U128(self.guild_deposit), self.guild_score_rule)
     }
 
+    /// Adds or updates an attribute players can set on their profile
     pub fn
-    get_score(&self) -> i32 {
-        env::log(format!("score now is {}.", self.score).as_bytes());
-        self.score
+    set_attribute_schema(&mut self, key: String, schema: AttributeSchema) {
+        self.only_owner();
+        assert!(
+            !key.is_empty() && key.len() <= MAX_ATTRIBUTE_KEY_LENGTH
+                && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+            "Attribute keys should have 1 to {} lowercase letters, digits or '_'.", MAX_ATTRIBUTE_KEY_LENGTH
+        );
+        assert!(0 < schema.max_length && schema.max_length <= MAX_ATTRIBUTE_LENGTH, "Attribute max length should be between 1 and {}.", MAX_ATTRIBUTE_LENGTH);
+        self.attribute_schema.insert(&key, &schema);
     }
 
+    /// Values already set for the key are hidden and dropped on the next change of their profile
     pub fn
-    get_username(&self) -> String {
-        return self.username.to_string()
+    remove_attribute_schema(&mut self, key: String) {
+        self.only_owner();
+        self.attribute_schema.remove(&key);
     }
 
     pub fn
-    is_empty(&self) -> bool {
-        return self.username.is_empty()
+    get_attribute_schema(&self) -> Vec<(String, AttributeSchema)> {
+        self.attribute_schema.to_vec()
+    }
+
+    /// Fee to rename before the cooldown is over, and the cooldown in nanoseconds
+    pub fn
+    set_rename_rules(&mut self, fee: U128, cooldown: u64) {
+        self.only_owner();
+        self.rename_fee = fee.0;
+        self.rename_cooldown = cooldown;
+    }
+
+    pub fn
+    get_rename_rules(&self) -> (U128, u64) {
+        (U128(self.rename_fee), self.rename_cooldown)
+    }
+
+    /// Sets the NFT contract whose items can be equipped with `nft_transfer_call`
+    pub fn
+    set_item_contract(&mut self, nft_contract_id: AccountId) {
+        self.only_owner();
+        assert!(env::is_valid_account_id(nft_contract_id.as_bytes()), "Item contract ID is invalid.");
+        self.item_contract_id = Some(nft_contract_id);
+    }
+
+    /// Allows the tokens of the NFT contract as avatars, see `set_avatar`.
+    /// Ownership is only as trustworthy as the contract answering `get_token_owner`.
+    pub fn
+    add_avatar_contract(&mut self, nft_contract_id: AccountId) {
+        self.only_owner();
+        assert!(env::is_valid_account_id(nft_contract_id.as_bytes()), "NFT contract ID is invalid.");
+        self.avatar_contracts.insert(&nft_contract_id);
+    }
+
+    pub fn
+    remove_avatar_contract(&mut self, nft_contract_id: AccountId) {
+        self.only_owner();
+        assert!(self.avatar_contracts.remove(&nft_contract_id), "Unknown avatar contract.");
+    }
+
+    pub fn
+    get_avatar_contracts(&self) -> Vec<AccountId> {
+        self.avatar_contracts.to_vec()
+    }
+
+    /// Bounds of the multiplier applied by equipped items, in basis points
+    pub fn
+    set_multiplier_bounds(&mut self, min: u32, max: u32) {
+        self.only_owner();
+        assert!(min <= max, "Minimum multiplier is above the maximum.");
+        self.multiplier_bounds = (min, max);
+    }
+
+    pub fn
+    get_multiplier_bounds(&self) -> (u32, u32) {
+        self.multiplier_bounds
+    }
+
+    /// Returns `true` when the item should go back to its owner
+    #[private]
+    pub fn
+    on_item_metadata(&mut self, account_id: AccountId, token_id: u64) -> bool {
+        let multiplier = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<Option<ItemMetadata>>(&value)
+                .ok()
+                .flatten()
+                .and_then(|metadata| metadata.score_multiplier()),
+            _ => None,
+        };
+        let account_hash = env::sha256(account_id.as_bytes());
+        match multiplier {
+            Some(multiplier) if self.equipped_items.get(&account_hash).is_none() => {
+                let item = EquippedItem {
+                    contract_id: self.item_contract_id.clone().unwrap(),
+                    token_id,
+                    multiplier,
+                };
+                self.equipped_items.insert(&account_hash, &item);
+                env::log(format!("{} equipped item {}", account_id, token_id).as_bytes());
+                false
+            }
+            _ => {
+                env::log(format!("item {} can not be equipped by {}", token_id, account_id).as_bytes());
+                true
+            }
+        }
+    }
+
+    #[private]
+    pub fn
+    on_item_unequipped(&mut self, account_id: AccountId, item: EquippedItem) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            env::log(format!("{} unequipped item {}", account_id, item.token_id).as_bytes());
+            return true;
+        }
+        // the item is still in custody, keep it equipped unless another item took the slot meanwhile
+        let account_hash = env::sha256(account_id.as_bytes());
+        if self.equipped_items.get(&account_hash).is_none() {
+            self.equipped_items.insert(&account_hash, &item);
+        } else {
+            env::log(format!("item {} of {} could not be returned", item.token_id, account_id).as_bytes());
+        }
+        false
+    }
+
+    /// Returns `true` when the account owns the token of the avatar
+    #[private]
+    pub fn
+    on_avatar_owner(&mut self, account_id: AccountId, avatar: Avatar, is_new: bool) -> bool {
+        // `get_token_owner` fails for burned tokens
+        let owner_id = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<String>(&value).ok(),
+            _ => None,
+        };
+        let account_hash = env::sha256(account_id.as_bytes());
+        if owner_id.as_ref() == Some(&account_id) {
+            if is_new && self.check_access(account_id.to_string()) {
+                self.avatars.insert(&account_hash, &avatar);
+                env::log(format!("{} set token {} of {} as avatar", account_id, avatar.token_id, avatar.contract_id).as_bytes());
+            }
+            return true;
+        }
+
+        // only remove the avatar checked, it may have been changed in the meantime
+        if self.avatars.get(&account_hash).as_ref() == Some(&avatar) {
+            self.avatars.remove(&account_hash);
+        }
+        env::log(format!("{} does not own token {} of {}", account_id, avatar.token_id, avatar.contract_id).as_bytes());
+        false
+    }
+
+    /// Sets the NFT contract minting season trophies, the leaderboard must be one of its minters
+    pub fn
+    set_trophy_contract(&mut self, nft_contract_id: AccountId) {
+        self.only_owner();
+        assert!(env::is_valid_account_id(nft_contract_id.as_bytes()), "Trophy contract ID is invalid.");
+        self.trophy_contract_id = Some(nft_contract_id);
+    }
+
+    #[private]
+    pub fn
+    on_season_payout(&mut self, season_id: u64, winner_index: u64, is_trophy: bool) -> bool {
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        let mut season = self.seasons.get(season_id).expect("Season not found");
+        let winner = &mut season.winners[winner_index as usize];
+        let status = if succeeded { PayoutStatus::Paid } else { PayoutStatus::Unpaid };
+        if is_trophy {
+            winner.trophy_status = status;
+        } else {
+            winner.prize_status = status;
+        }
+        env::log(format!(
+            "season {} {} for {} {}",
+            season_id,
+            if is_trophy { "trophy" } else { "prize" },
+            winner.account_id,
+            if succeeded { "delivered" } else { "failed, will be retried" }
+        ).as_bytes());
+        self.seasons.replace(season_id, &season);
+        succeeded
+    }
+
+    pub fn
+    get_list_user(&self, account_id: AccountId) -> Vec<Profile> {
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let map = self.state.get(&escrow_hash).unwrap();
+        let keys = map.keys();
+        let mut vec:Vec<Profile> = vec![];
+        for key in keys {
+            vec.push(map.get(&key).unwrap())
+        }
+        return vec;
     }
 
     fn
-    rewarded(&mut self, timestamp: i64) {
-        self.hourly_reward_at = timestamp;
+    sorted_players(&self) -> Vec<(String, Profile)> {
+        sort_characters(self.flat_users())
     }
-}
 
-impl Serialize for Profile {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-    {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("Profile", 2)?;
-        state.serialize_field("score", &self.score)?;
-        state.serialize_field("username", &self.username)?;
-        state.end()
+    /// Players shown in the public leaderboard views, ordered by score
+    fn
+    public_players(&self) -> Vec<(String, Profile)> {
+        self.sorted_players().into_iter()
+            .filter(|(_, profile)| self.is_publicly_ranked(&profile.get_username()))
+            .collect()
     }
-}
 
-impl Clone for Profile {
-    fn clone(&self) -> Self {
-        return Profile{
-            username: self.username.to_string(),
-            hourly_reward_at: self.hourly_reward_at,
-            score: self.score,
+    fn
+    is_publicly_ranked(&self, account_id: &AccountId) -> bool {
+        self.get_privacy(account_id.to_string()).rank == Visibility::Public
+    }
+
+    fn
+    leaderboard_entry(&self, character: String, profile: Profile) -> LeaderBoardEntry {
+        LeaderBoardEntry {
+            display_name: self.get_display_name(profile.get_username()),
+            avatar: self.get_avatar(profile.get_username()),
+            account_id: profile.get_username(),
+            character,
+            score: profile.score,
         }
     }
 
-    fn clone_from(&mut self, source: &Self) {
-        self.score = source.score;
-        self.hourly_reward_at = source.hourly_reward_at;
-        self.username = source.username.to_string();
+    fn
+    flat_users(&self) -> Vec<(String, Profile)> {
+        let mut vec: Vec<(String, Profile)> = vec![];
+        for value in self.state.values() {
+            vec.append(&mut value.to_vec())
+        };
+
+        return vec;
     }
-}
 
-// --- START: Utility --- //
-fn get_sorted_profiles_by_score(list: &UnorderedMap<String, Profile>, only_highest: bool) -> Vec<(String, Profile)> {
-    let mut result = list.to_vec();
-    return mut_to_sorted_vec(&mut result, only_highest);
-}
+    /// helper function determining contract ownership
+    fn only_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only contract owner can call this method.");
+    }
+
+    fn only_moderator(&self) {
+        assert!(self.moderators.contains(&env::predecessor_account_id()), "Only moderators can call this method.");
+    }
+
+    fn only_reporter(&self) {
+        let account_id = env::predecessor_account_id();
+        assert!(account_id == self.owner_id || self.reporters.contains(&account_id), "Only the owner or a reporter can submit scores directly.");
+        assert!(self.server_keys.is_empty(), "Scores should be signed by a game server, see `submit_attested_score`.");
+    }
+
+    /// Checks the attestation is for the caller and signed by a game server, then marks its nonce as used
+    fn use_attestation(&mut self, attestation: &ScoreAttestation, signature: &Base64VecU8) {
+        assert_eq!(attestation.account_id, env::predecessor_account_id(), "Only the account of the attestation can submit it.");
+        assert!(env::block_timestamp() <= attestation.expiry.0, "The attestation has expired.");
+        // bound to this contract, an attestation for another deployment is not valid here
+        let message = (env::current_account_id(), attestation).try_to_vec().unwrap();
+        let signature = ed25519_dalek::Signature::try_from(&signature.0[..]).expect("Invalid signature.");
+        let signed = self.server_keys.iter().any(|key| {
+            ed25519_dalek::PublicKey::from_bytes(&key.0[1..])
+                .map(|key| key.verify(&message, &signature).is_ok())
+                .unwrap_or(false)
+        });
+        assert!(signed, "The attestation is not signed by a game server.");
+
+        let nonce_key = env::sha256(&(&attestation.account_id, attestation.nonce.0).try_to_vec().unwrap());
+        assert!(self.used_nonces.get(&nonce_key).is_none(), "The attestation was already used.");
+        self.used_nonces.insert(&nonce_key, &attestation.expiry.0);
+        self.nonce_expiries.insert(&(attestation.expiry.0, nonce_key), &());
+    }
+
+    /// Scores of a commit-reveal board are only submitted by `reveal_score` until its reveal deadline
+    fn assert_not_commit_reveal(&self, board_id: &String) {
+        let active = self.commit_reveal.get(board_id).is_some_and(|rules| env::block_timestamp() <= rules.reveal_end.0);
+        assert!(!active, "Scores of board {} should be committed, see `commit_score`.", board_id);
+    }
+
+    fn get_identity(&self, account_hash: &AccountIdHash) -> Option<Identity> {
+        self.identities.get(account_hash).or_else(|| match &self.legacy_identities {
+            Some(legacy) => legacy.get(account_hash).map(Identity::from),
+            None => None
+        })
+    }
+
+    /// Checks the caller can edit the info of the account, returns `false` if it has no profile
+    fn can_edit_info(&self, account_id: &AccountId) -> bool {
+        let predecessor = env::predecessor_account_id();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let is_editor = self.profile_editors.get(&escrow_hash).unwrap_or_default().contains(&predecessor);
+        assert!(&predecessor == account_id || is_editor, "Only the profile owner or its editors can edit its info.");
+        if !self.check_access(account_id.to_string()) {
+            env::log("not found info to set".as_bytes());
+            return false;
+        }
+        true
+    }
+
+    /// Checks the viewer can see what the account shares with the given visibility, `None` is anyone
+    fn can_view(&self, account_id: &AccountId, visibility: Visibility, viewer_id: Option<&AccountId>) -> bool {
+        match (visibility, viewer_id) {
+            (Visibility::Public, _) => true,
+            (_, None) => false,
+            (_, Some(viewer_id)) if viewer_id == account_id => true,
+            (Visibility::FriendsOnly, Some(viewer_id)) => self.get_friends(account_id.to_string()).contains(viewer_id),
+            (Visibility::Hidden, _) => false,
+        }
+    }
+
+    /// Characters of the account and its friends whose rank the viewer can see, `None` is anyone
+    fn friends_leaderboard(&self, account_id: AccountId, viewer_id: Option<&AccountId>, from: u64, limit: u64) -> Vec<LeaderBoardEntry> {
+        let mut players: Vec<(String, Profile)> = vec![];
+        let mut accounts = self.get_friends(account_id.to_string());
+        accounts.push(account_id);
+        accounts.retain(|player_id| self.can_view(player_id, self.get_privacy(player_id.to_string()).rank, viewer_id));
+        for player_id in accounts {
+            if let Some(profiles) = self.state.get(&env::sha256(player_id.as_bytes())) {
+                players.append(&mut profiles.to_vec());
+            }
+        }
+
+        sort_characters(players).into_iter()
+            .skip(from as usize)
+            .take(limit as usize)
+            .map(|(character, profile)| self.leaderboard_entry(character, profile))
+            .collect()
+    }
+
+    fn profile_info(&self, account_id: AccountId, viewer_id: Option<&AccountId>) -> Option<ProfileInfo> {
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let identity = self.get_identity(&escrow_hash);
+        let best = self.state.get(&escrow_hash)
+            .and_then(|profiles| get_sorted_profiles_by_score(&profiles, true).into_iter().next())
+            .map(|(_, profile)| profile);
+        if identity.is_none() && best.is_none() {
+            return None;
+        }
+
+        let visible = self.can_view(&account_id, self.get_privacy(account_id.to_string()).info, viewer_id);
+        let identity = if visible { identity.unwrap_or_default() } else { Identity::default() };
+        let best = best.unwrap_or_default();
+        Some(ProfileInfo {
+            first_name: identity.first_name,
+            last_name: identity.last_name,
+            age: identity.age,
+            attributes: if visible { self.get_attributes(&escrow_hash) } else { HashMap::new() },
+            account_id,
+            score: best.score,
+            hourly_reward_at: best.hourly_reward_at,
+        })
+    }
+
+    fn save_identity(&mut self, account_hash: &AccountIdHash, identity: &Identity) {
+        self.remove_identity(account_hash);
+        self.identities.insert(account_hash, identity);
+    }
+
+    fn remove_identity(&mut self, account_hash: &AccountIdHash) {
+        self.identities.remove(account_hash);
+        if let Some(legacy) = &mut self.legacy_identities {
+            legacy.remove(account_hash);
+        }
+    }
+
+    /// Attributes of the account whose key is still in the schema
+    fn get_attributes(&self, account_hash: &AccountIdHash) -> HashMap<String, String> {
+        let mut attributes = self.attributes.get(account_hash).unwrap_or_default();
+        attributes.retain(|key, _| self.attribute_schema.get(key).is_some());
+        attributes
+    }
+
+    fn save_attributes(&mut self, account_hash: &AccountIdHash, attributes: &HashMap<String, String>) {
+        if attributes.is_empty() {
+            self.attributes.remove(account_hash);
+        } else {
+            self.attributes.insert(account_hash, attributes);
+        }
+    }
+
+    /// Best score among the characters of the account
+    fn best_score(&self, account_hash: &AccountIdHash) -> i32 {
+        self.state.get(account_hash)
+            .and_then(|profiles| get_sorted_profiles_by_score(&profiles, true).into_iter().next())
+            .map_or(0, |(_, profile)| profile.score)
+    }
+
+    /// Guild of the caller with its role in it, panics if it is not in a guild
+    fn caller_guild(&self) -> (u64, Guild, GuildRole) {
+        let account_id = env::predecessor_account_id();
+        let guild_id = self.member_guilds.get(&env::sha256(account_id.as_bytes())).expect("Not in a guild.");
+        let guild = self.guilds.get(&guild_id).unwrap();
+        let role = guild.member(&account_id).unwrap().role;
+        (guild_id, guild, role)
+    }
+
+    fn add_guild_member(&mut self, guild_id: u64, guild: &mut Guild, account_id: &AccountId) {
+        let account_hash = env::sha256(account_id.as_bytes());
+        assert!(self.member_guilds.get(&account_hash).is_none(), "{} is already in a guild.", account_id);
+        assert!(guild.members.len() < MAX_GUILD_MEMBERS, "A guild can not have more than {} members.", MAX_GUILD_MEMBERS);
+        guild.invites.retain(|invited_id| invited_id != account_id);
+        guild.join_requests.retain(|requester_id| requester_id != account_id);
+        guild.members.push(GuildMember {
+            account_id: account_id.to_string(),
+            role: GuildRole::Member,
+            best_score: self.best_score(&account_hash),
+        });
+        self.member_guilds.insert(&account_hash, &guild_id);
+        self.guilds.insert(&guild_id, guild);
+        env::log(format!("{} joined guild {}", account_id, guild_id).as_bytes());
+    }
+
+    /// Removes the account from its guild, the guild is handed over or disbanded when it was its founder
+    fn remove_guild_member(&mut self, account_id: &AccountId) {
+        let guild_id = match self.member_guilds.remove(&env::sha256(account_id.as_bytes())) {
+            Some(guild_id) => guild_id,
+            None => return
+        };
+        let mut guild = self.guilds.get(&guild_id).unwrap();
+        let was_founder = guild.member(account_id).unwrap().role == GuildRole::Founder;
+        guild.members.retain(|member| &member.account_id != account_id);
+        env::log(format!("{} left guild {}", account_id, guild_id).as_bytes());
+        if guild.members.is_empty() {
+            self.guilds.remove(&guild_id);
+            self.guild_names.remove(&display_name_key(&guild.name));
+            if guild.deposit.0 > 0 {
+                Promise::new(guild.deposit_owner.to_string()).transfer(guild.deposit.0);
+            }
+            if guild.treasury > 0 {
+                log_event("guild_treasury_debit", json!({
+                    "guild_id": guild_id,
+                    "proposal_id": null,
+                    "receiver_id": account_id,
+                    "amount": guild.treasury,
+                    "balance": 0,
+                }));
+            }
+            env::log(format!("guild {} disbanded", guild_id).as_bytes());
+            return;
+        }
+
+        if was_founder {
+            let successor = guild.members.iter().position(|member| member.role == GuildRole::Officer).unwrap_or(0);
+            guild.members[successor].role = GuildRole::Founder;
+            env::log(format!("{} now leads guild {}", guild.members[successor].account_id, guild_id).as_bytes());
+        }
+        self.guilds.insert(&guild_id, &guild);
+    }
+
+    /// Credits the treasury of the guild of the account with its share of the reward, returns the share
+    fn pay_guild_tithe(&mut self, account_id: &AccountId, reward: i32) -> i32 {
+        let guild_id = match self.member_guilds.get(&env::sha256(account_id.as_bytes())) {
+            Some(guild_id) => guild_id,
+            None => return 0
+        };
+        let mut guild = self.guilds.get(&guild_id).unwrap();
+        let tithe = reward * guild.tithe_percent as i32 / 100;
+        if tithe > 0 {
+            guild.treasury += tithe as u64;
+            self.guilds.insert(&guild_id, &guild);
+            log_event("guild_treasury_credit", json!({ "guild_id": guild_id, "account_id": account_id, "amount": tithe, "balance": guild.treasury }));
+        }
+        tithe
+    }
+
+    fn join_region(&mut self, account_id: &AccountId, region: &PlayerRegion) {
+        let mut ranks = self.region_ranks.get(&region.code)
+            .unwrap_or_else(|| RankedIndex::new(format!("region-{}", region.code).into_bytes()));
+        for (character, profile) in self.get_characters(account_id.to_string()) {
+            ranks.set(account_id, &character, profile.score);
+        }
+        self.region_ranks.insert(&region.code, &ranks);
+        let mut stats = self.region_stats.get(&region.code).unwrap_or_else(|| RegionEntry::new(&region.code));
+        stats.players += 1;
+        stats.total_score += region.best_score as i64;
+        self.region_stats.insert(&region.code, &stats.averaged());
+    }
+
+    fn leave_region(&mut self, account_id: &AccountId, region: &PlayerRegion) {
+        let mut ranks = self.region_ranks.get(&region.code).unwrap();
+        ranks.remove_account(account_id);
+        self.region_ranks.insert(&region.code, &ranks);
+        let mut stats = self.region_stats.get(&region.code).unwrap();
+        stats.players -= 1;
+        stats.total_score -= region.best_score as i64;
+        if stats.players == 0 {
+            self.region_stats.remove(&region.code);
+        } else {
+            self.region_stats.insert(&region.code, &stats.averaged());
+        }
+    }
+
+    /// Ranks the character in the region of the account with its score, or removes it with `None`
+    fn rank_in_region(&mut self, account_hash: &AccountIdHash, account_id: &AccountId, character: &str, score: Option<i32>) {
+        if let Some(region) = self.player_regions.get(account_hash) {
+            let mut ranks = self.region_ranks.get(&region.code).unwrap();
+            match score {
+                Some(score) => ranks.set(account_id, character, score),
+                None => ranks.remove(account_id, character),
+            }
+            self.region_ranks.insert(&region.code, &ranks);
+        }
+    }
+
+    /// Counts the current best score of the account in the stats of its region, which may be lower
+    fn refresh_region_score(&mut self, account_hash: &AccountIdHash) {
+        if let Some(mut region) = self.player_regions.get(account_hash) {
+            let best_score = self.best_score(account_hash);
+            if best_score != region.best_score {
+                let mut stats = self.region_stats.get(&region.code).unwrap();
+                stats.total_score += best_score as i64 - region.best_score as i64;
+                self.region_stats.insert(&region.code, &stats.averaged());
+                region.best_score = best_score;
+                self.player_regions.insert(account_hash, &region);
+            }
+        }
+    }
+
+    /// Saves a score in the history of the character and its rank when it is its best score
+    fn record_score(&mut self, account_id: AccountId, character: Option<String>, board_id: String, score: i32, replay: Option<ReplayProof>) -> bool {
+        assert!(!board_id.is_empty() && board_id.len() <= MAX_BOARD_ID_LENGTH, "Board ids should have 1 to {} characters.", MAX_BOARD_ID_LENGTH);
+        if let Some(replay) = &replay {
+            assert!(replay.hash.len() == 64 && replay.hash.chars().all(|c| c.is_ascii_hexdigit()), "Replay hash should be a hex encoded SHA-256.");
+            if let Some(cid) = &replay.cid {
+                assert!(!cid.is_empty() && cid.len() <= MAX_CID_LENGTH && cid.chars().all(|c| c.is_ascii_alphanumeric()),
+                        "Replay CID should have 1 to {} alphanumeric characters.", MAX_CID_LENGTH);
+            }
+        }
+        let account_id = self.get_primary_account(account_id);
+        let account_ref: &str = account_id.as_ref();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        if !self.check_access(account_ref.to_string()) {
+            return false;
+        };
+        let map_profile = self.state.get(&escrow_hash).unwrap();
+        // the first character is keyed by the account id, see `grant_access`
+        let character = character.unwrap_or_else(|| account_ref.to_string());
+        if map_profile.get(&character).is_none() {
+            return false;
+        };
+        let score = self.apply_score_multiplier(&escrow_hash, score);
+        let history = self.score_histories.get(&history_key(&escrow_hash, &character)).unwrap_or_default();
+        let board_best = history.boards.get(&board_id).map(|stats| stats.best.score);
+        let board_submissions = history.boards.get(&board_id).map_or(0, |stats| stats.submissions);
+        assert!(board_best.is_some() || history.boards.len() < MAX_BOARDS_PER_CHARACTER, "A character can not play more than {} boards.", MAX_BOARDS_PER_CHARACTER);
+        let ranked_best = self.ranked_best(&account_id, &character, &board_id);
+        if let Err(rejection) = self.check_board_rules(&escrow_hash, &board_id, score, board_best) {
+            log_event("score_rejected", json!({
+                "account_id": account_id,
+                "character": character,
+                "board_id": board_id,
+                "score": score,
+                "code": rejection,
+            }));
+            for counts_key in [board_id.as_bytes().to_vec(), board_key(&escrow_hash, &board_id)] {
+                let mut counts = self.rejection_counts.get(&counts_key).unwrap_or_default();
+                counts.add(rejection);
+                self.rejection_counts.insert(&counts_key, &counts);
+            }
+            return false;
+        }
+        let record = ScoreRecord {
+            score,
+            board_id: board_id.to_string(),
+            timestamp: env::block_timestamp(),
+            replay: replay.clone(),
+        };
+        if let Some(reason) = self.check_outlier(&board_id, score, ranked_best, board_submissions) {
+            // saved once approved, see `approve_score`
+            let review_id = self.next_review_id;
+            self.next_review_id += 1;
+            log_event("score_pending_review", json!({
+                "review_id": review_id,
+                "account_id": account_id,
+                "character": character,
+                "board_id": board_id,
+                "score": score,
+                "reason": reason,
+            }));
+            self.pending_reviews.insert(&review_id, &PendingReview {
+                account_id,
+                character,
+                board_id,
+                score,
+                replay,
+                submitted_at: env::block_timestamp(),
+                reason,
+            });
+            return false;
+        }
+        self.rank_score(account_id, character, record, ranked_best)
+    }
+
+    /// Best score of the character on the board, the default board ranks the profile
+    fn ranked_best(&self, account_id: &AccountId, character: &str, board_id: &String) -> Option<i32> {
+        if board_id == DEFAULT_BOARD_ID {
+            let profiles = self.state.get(&env::sha256(account_id.as_bytes()))?;
+            return profiles.get(&character.to_string()).map(|profile| profile.get_score());
+        }
+        self.board_ranks.get(board_id)?.get(account_id, character)
+    }
+
+    /// Ranks a new best score, provisionally when it is in the top of its board, see `set_challenge_rules`,
+    /// and saves the other scores without ranking them
+    fn rank_score(&mut self, account_id: AccountId, character: String, record: ScoreRecord, ranked_best: Option<i32>) -> bool {
+        let (board_id, score) = (record.board_id.to_string(), record.score);
+        let pending_key = commit_key(&account_id, &character, &board_id);
+        let pending = self.pending_provisionals.get(&pending_key)
+            .map(|score_id| (score_id, self.provisional_scores.get(&score_id).unwrap()));
+        if ranked_best.is_some_and(|best| best >= score) || pending.as_ref().is_some_and(|(_, provisional)| provisional.score >= score) {
+            return self.confirm_score(&account_id, &character, record, false);
+        }
+
+        let rules = &self.challenge_rules;
+        let ahead = self.board_ranks.get(&board_id).map_or(0, |ranks| ranks.count_from(score, rules.top as u64));
+        if rules.top > 0 && ahead < rules.top as u64 {
+            if let Some((score_id, provisional)) = pending {
+                // a character has one provisional score per board, the replaced one is kept in the history
+                assert!(provisional.dispute.is_none(), "The provisional score {} of the character is disputed, see `resolve_dispute`.", score_id);
+                self.remove_provisional(score_id, &provisional);
+                log_event("score_provisional_replaced", json!({ "score_id": score_id }));
+                self.confirm_score(&provisional.account_id, &provisional.character, provisional.record(), false);
+            }
+            let score_id = self.next_provisional_id;
+            self.next_provisional_id += 1;
+            self.pending_provisionals.insert(&pending_key, &score_id);
+            log_event("score_provisional", json!({
+                "score_id": score_id,
+                "account_id": account_id,
+                "character": character,
+                "board_id": board_id,
+                "score": score,
+            }));
+            self.provisional_scores.insert(&score_id, &ProvisionalScore {
+                account_id,
+                character,
+                board_id,
+                score,
+                replay: record.replay,
+                // the challenge window of an approved score starts at its approval
+                submitted_at: env::block_timestamp(),
+                dispute: None,
+            });
+            return true;
+        }
+        self.confirm_score(&account_id, &character, record, true)
+    }
+
+    /// Saves a confirmed score in the history of the character and the stats of its board,
+    /// and ranks it when `rank` is set and it is the best score of the character
+    fn confirm_score(&mut self, account_id: &AccountId, character: &str, record: ScoreRecord, rank: bool) -> bool {
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        // the character may have been deleted while its score was provisional
+        if self.state.get(&escrow_hash).and_then(|profiles| profiles.get(&character.to_string())).is_none() {
+            return false;
+        }
+        let key = history_key(&escrow_hash, character);
+        let mut history = self.score_histories.get(&key).unwrap_or_default();
+        history.push(record.clone(), self.history_depth);
+        self.score_histories.insert(&key, &history);
+        let mut distribution = self.board_distributions.get(&record.board_id).unwrap_or_default();
+        distribution.add(record.score);
+        self.board_distributions.insert(&record.board_id, &distribution);
+        rank && self.apply_best_score(account_id, character, &record.board_id, record.score)
+    }
+
+    /// Checks nothing submitted by the account is still waiting to be saved under its name
+    fn assert_no_pending_scores(&self, account_id: &AccountId) {
+        assert!(self.provisional_scores.values().all(|provisional| &provisional.account_id != account_id),
+                "The provisional scores of the profile should be final before transferring it.");
+        assert!(self.pending_reviews.values().all(|review| &review.account_id != account_id),
+                "The scores of the profile under review should be resolved before transferring it.");
+        assert!(self.sessions.values().all(|session| &session.player_id != account_id),
+                "The game sessions of the profile should be ended before transferring it.");
+        let characters = self.state.get(&env::sha256(account_id.as_bytes())).map(|profiles| profiles.keys_as_vector().to_vec()).unwrap_or_default();
+        for character in characters {
+            assert!(self.commit_reveal.keys().all(|board_id| self.score_commits.get(&commit_key(account_id, &character, &board_id)).is_none()),
+                    "The committed scores of the profile should be revealed before transferring it.");
+        }
+    }
+
+    /// Removes the provisional score and the pending entry of its character
+    fn remove_provisional(&mut self, score_id: u64, provisional: &ProvisionalScore) {
+        self.provisional_scores.remove(&score_id);
+        let pending_key = commit_key(&provisional.account_id, &provisional.character, &provisional.board_id);
+        if self.pending_provisionals.get(&pending_key) == Some(score_id) {
+            self.pending_provisionals.remove(&pending_key);
+        }
+    }
+
+    /// Checks the score against the rules of the board, and counts the submission when it passes
+    fn check_board_rules(&mut self, account_hash: &AccountIdHash, board_id: &String, score: i32, board_best: Option<i32>) -> Result<(), ScoreRejection> {
+        let rules = match self.board_rules.get(board_id) {
+            Some(rules) => rules,
+            None => return Ok(())
+        };
+        if rules.min_score.is_some_and(|min_score| score < min_score) {
+            return Err(ScoreRejection::BelowMinimum);
+        }
+        if rules.max_score.is_some_and(|max_score| score > max_score) {
+            return Err(ScoreRejection::AboveMaximum);
+        }
+        if let (Some(max_improvement), Some(best)) = (rules.max_improvement, board_best) {
+            if score as i64 - best as i64 > max_improvement as i64 {
+                return Err(ScoreRejection::ImprovementTooLarge);
+            }
+        }
+
+        let now = env::block_timestamp();
+        let key = board_key(account_hash, board_id);
+        let mut window = self.submission_windows.get(&key).unwrap_or_default();
+        if window.submissions > 0 && rules.min_interval.is_some_and(|min_interval| now - window.last_at < min_interval) {
+            return Err(ScoreRejection::TooFrequent);
+        }
+        if window.day != now / DAY {
+            window.day = now / DAY;
+            window.day_submissions = 0;
+        }
+        if rules.daily_cap.is_some_and(|daily_cap| window.day_submissions >= daily_cap) {
+            return Err(ScoreRejection::DailyCapReached);
+        }
+        window.last_at = now;
+        window.submissions += 1;
+        window.day_submissions += 1;
+        self.submission_windows.insert(&key, &window);
+        Ok(())
+    }
+
+    /// Why the score should be reviewed by a moderator before it is ranked, if it should
+    fn check_outlier(&self, board_id: &String, score: i32, best_score: Option<i32>, board_submissions: u64) -> Option<OutlierReason> {
+        let rules = self.outlier_rules.as_ref()?;
+        if let Some(distribution) = self.board_distributions.get(board_id) {
+            // at least one point, scores above the mean of identical samples are not all outliers
+            let deviation = distribution.variance().sqrt().max(1.0);
+            if distribution.count >= rules.min_samples && score as f64 - distribution.mean > rules.max_deviations * deviation {
+                return Some(OutlierReason::Deviation);
+            }
+        }
+        if board_submissions < rules.new_account_submissions && best_score.is_none_or(|best| best < score) {
+            // top 0.1% of the characters ranked on the board
+            let ranked = self.board_ranks.get(board_id).map_or(0, |ranks| ranks.len());
+            let ahead = self.board_ranks.get(board_id).map_or(0, |ranks| ranks.count_from(score, ranked / 1000 + 1));
+            if ahead * 1000 < ranked {
+                return Some(OutlierReason::NewAccountTop);
+            }
+        }
+        None
+    }
+
+    /// Ranks the character on the board with the score if it is its best one
+    fn apply_best_score(&mut self, account_id: &AccountId, character: &str, board_id: &String, score: i32) -> bool {
+        if board_id != DEFAULT_BOARD_ID {
+            let ranks = self.board_ranks.get(board_id);
+            if ranks.is_some_and(|ranks| ranks.get(account_id, character).is_some_and(|best| best >= score)) {
+                return false;
+            }
+            self.rank_on_board(account_id, character, board_id, Some(score));
+            return true;
+        }
+        // the default board also ranks the profile, see `get_top_players`
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut map_profile = match self.state.get(&escrow_hash) {
+            Some(map_profile) => map_profile,
+            None => return false
+        };
+        let mut profile = match map_profile.get(&character.to_string()) {
+            Some(profile) if profile.get_score() < score => profile,
+            _ => return false
+        };
+        profile.set_score(score);
+        map_profile.insert(&character.to_string(), &profile);
+        self.state.insert(&escrow_hash, &map_profile);
+        self.rank_on_board(account_id, character, board_id, Some(score));
+        self.refresh_guild_score(account_id);
+        self.rank_in_region(&escrow_hash, account_id, character, Some(score));
+        self.refresh_region_score(&escrow_hash);
+        true
+    }
+
+    /// Sets the best score of the character on the board, or removes it with `None`
+    fn rank_on_board(&mut self, account_id: &AccountId, character: &str, board_id: &String, score: Option<i32>) {
+        let mut ranks = self.board_ranks.get(board_id).unwrap_or_else(|| RankedIndex::new(board_ranks_prefix(board_id)));
+        match score {
+            Some(score) => ranks.set(account_id, character, score),
+            None => ranks.remove(account_id, character),
+        }
+        self.board_ranks.insert(board_id, &ranks);
+    }
+
+    /// Moves the character to its new account and name on the boards it played, or removes it from them with `None`
+    fn move_board_ranks(&mut self, account_id: &AccountId, character: &str, history: &ProfileHistory, to: Option<(&AccountId, &str)>) {
+        for board_id in history.boards.keys() {
+            let best = self.board_ranks.get(board_id).and_then(|ranks| ranks.get(account_id, character));
+            self.rank_on_board(account_id, character, board_id, None);
+            if let (Some((new_account_id, new_character)), Some(best)) = (to, best) {
+                self.rank_on_board(new_account_id, new_character, board_id, Some(best));
+            }
+        }
+    }
+
+    /// Removes the session and refunds its deposit
+    fn close_session(&mut self, session_id: u64, session: &GameSession) {
+        self.sessions.remove(&session_id);
+        let mut stats = self.get_session_stats(session.board_id.to_string());
+        stats.open -= 1;
+        if !session.submitted {
+            stats.abandoned += 1;
+        }
+        self.session_stats.insert(&session.board_id, &stats);
+        if session.deposit.0 > 0 {
+            Promise::new(session.player_id.to_string()).transfer(session.deposit.0);
+        }
+    }
+
+    /// Sets the score of the account in its guild to its current best score, which may be lower
+    fn refresh_guild_score(&mut self, account_id: &AccountId) {
+        let account_hash = env::sha256(account_id.as_bytes());
+        if let Some(guild_id) = self.member_guilds.get(&account_hash) {
+            let best_score = self.best_score(&account_hash);
+            let mut guild = self.guilds.get(&guild_id).unwrap();
+            for member in guild.members.iter_mut().filter(|member| &member.account_id == account_id) {
+                member.best_score = best_score;
+            }
+            self.guilds.insert(&guild_id, &guild);
+        }
+    }
+
+    fn add_friend(&mut self, account_id: &AccountId, friend_id: &AccountId) {
+        let account_hash = env::sha256(account_id.as_bytes());
+        let mut friends = self.friends.get(&account_hash).unwrap_or_default();
+        assert!(friends.len() < MAX_FRIENDS, "A player can not have more than {} friends.", MAX_FRIENDS);
+        friends.push(friend_id.to_string());
+        self.friends.insert(&account_hash, &friends);
+    }
+
+    fn unlink_account(&mut self, account_id: &AccountId) {
+        let primary_account_id = match self.primary_accounts.remove(&env::sha256(account_id.as_bytes())) {
+            Some(primary_account_id) => primary_account_id,
+            None => return
+        };
+        let primary_hash = env::sha256(primary_account_id.as_bytes());
+        let mut linked_accounts = self.linked_accounts.get(&primary_hash).unwrap_or_default();
+        linked_accounts.retain(|linked_account_id| linked_account_id != account_id);
+        if linked_accounts.is_empty() {
+            self.linked_accounts.remove(&primary_hash);
+        } else {
+            self.linked_accounts.insert(&primary_hash, &linked_accounts);
+        }
+        log_event("account_unlinked", json!({ "primary_account_id": primary_account_id, "linked_account_id": account_id }));
+    }
+
+    fn save_display_name(&mut self, account_id: &AccountId, name: String, key: String) {
+        let account_hash = env::sha256(account_id.as_bytes());
+        self.remove_display_name(&account_hash);
+        self.name_owners.insert(&key, account_id);
+        self.display_names.insert(&account_hash, &DisplayName {
+            name,
+            key,
+            changed_at: env::block_timestamp(),
+        });
+    }
+
+    fn remove_display_name(&mut self, account_hash: &AccountIdHash) {
+        if let Some(display_name) = self.display_names.remove(account_hash) {
+            self.name_owners.remove(&display_name.key);
+        }
+    }
+
+    fn apply_score_multiplier(&self, account_hash: &AccountIdHash, score: i32) -> i32 {
+        match self.equipped_items.get(account_hash) {
+            Some(item) => {
+                let (min, max) = self.multiplier_bounds;
+                let multiplied = score as i64 * item.multiplier.clamp(min, max) as i64 / MULTIPLIER_DENOMINATOR as i64;
+                multiplied.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+            }
+            None => score
+        }
+    }
+}
+
+#[near_bindgen]
+impl NEP4 for ProfileState {
+    fn grant_access(&mut self, account_id: AccountId) {
+        let account_ref: &str = account_id.as_ref();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        assert!(self.primary_accounts.get(&escrow_hash).is_none(), "Linked accounts play as their primary account.");
+        if self.check_access(account_ref.to_string()) {
+            env::log("existed!".as_bytes());
+            println!("existed!");
+        } else {
+            let new_profile = &Profile::new(String::from(account_ref));
+            let mut new_map = UnorderedMap::new(profiles_prefix(&escrow_hash));
+            new_map.insert(&String::from(account_ref), new_profile);
+            self.state.insert(&escrow_hash, &new_map);
+            env::log("granted!".as_bytes());
+            println!("granted!");
+        }
+    }
+
+    fn revoke_access(&mut self, account_id: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert!(predecessor == account_id || predecessor == self.owner_id, "Only the account or the contract owner can revoke its access.");
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        if self.check_access(account_id.to_string()) {
+            // clear the characters, a profile granted again reuses their storage prefix
+            if let Some(mut profiles) = self.state.remove(&escrow_hash) {
+                for character in profiles.keys() {
+                    if let Some(history) = self.score_histories.remove(&history_key(&escrow_hash, &character)) {
+                        self.move_board_ranks(&account_id, &character, &history, None);
+                    }
+                }
+                profiles.clear();
+            }
+            self.remove_identity(&escrow_hash);
+            self.profile_editors.remove(&escrow_hash);
+            self.remove_display_name(&escrow_hash);
+            self.attributes.remove(&escrow_hash);
+            self.avatars.remove(&escrow_hash);
+            self.privacy.remove(&escrow_hash);
+            self.profile_transfers.remove(&escrow_hash);
+            for linked_account_id in self.linked_accounts.remove(&escrow_hash).unwrap_or_default() {
+                self.primary_accounts.remove(&env::sha256(linked_account_id.as_bytes()));
+            }
+            for friend_id in self.friends.remove(&escrow_hash).unwrap_or_default() {
+                remove_from_list(&mut self.friends, &env::sha256(friend_id.as_bytes()), &account_id);
+            }
+            self.friend_requests.remove(&escrow_hash);
+            self.remove_guild_member(&account_id);
+            if let Some(region) = self.player_regions.remove(&escrow_hash) {
+                self.leave_region(&account_id, &region);
+            }
+            env::log("revoked!".as_bytes());
+            println!("revoked!");
+        } else {
+            env::log("not found to revoke!".as_bytes());
+            println!("not found to revoke!");
+        }
+    }
+
+    fn check_access(&self, account_id: AccountId) -> bool {
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        return match self.state.get(&escrow_hash) {
+            Some(_) => {
+                true
+            }
+            _ => {
+                false
+            }
+        };
+    }
+}
+
+#[near_bindgen]
+impl LeaderBoard for ProfileState {
+    fn get_top_players(&self) -> Vec<LeaderBoardEntry> {
+        self.public_players().into_iter()
+            .map(|(character, profile)| self.leaderboard_entry(character, profile))
+            .collect()
+    }
+
+    fn get_reward(&mut self, account_id: AccountId) -> i32 {
+        let account_id = self.get_primary_account(account_id);
+        let account_ref: &str = account_id.as_ref();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        if !self.check_access(account_ref.to_string()) || self.is_recently_rewarded(account_ref.to_string()){
+            return 0;
+        }
+
+        let map_users = self.state.get(&escrow_hash);
+        let top_users = get_sorted_profiles_by_score(&map_users.unwrap(), true);
+        let highest_score = top_users[0].1.score;
+        let mut reward = 0;
+        for max in REWARD_RANGE {
+          if (highest_score as i32) < max.0 {
+              reward = max.1;
+              break;
+          }
+        };
+
+        let reward_time = Utc::now().timestamp_millis();
+        // update the characters in place, a new map under the same prefix would see their stale entries
+        let mut update_map = self.state.get(&escrow_hash).unwrap();
+        if reward > 0 {
+            let keys: Vec<String> = update_map.keys().collect();
+            for key in keys {
+                let mut update_profile = update_map.get(&key).unwrap();
+                update_profile.rewarded(reward_time);
+                update_map.insert(&key, &update_profile);
+            }
+
+            self.state.insert(&escrow_hash, &update_map);
+            reward -= self.pay_guild_tithe(&account_id, reward);
+        }
+
+        return reward;
+    }
+
+    fn is_recently_rewarded(&self, account_id: AccountId) -> bool {
+        let account_id = self.get_primary_account(account_id);
+        let account_ref: &str = account_id.as_ref();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        if !self.check_access(account_ref.to_string()) {
+            return true;
+        }
+
+        let user = self.state.get(&escrow_hash).unwrap().values().next().unwrap();
+        return Utc::now().timestamp_millis() - user.hourly_reward_at < REWARD_FREQUENCY;
+    }
+
+    fn save_new_score(&mut self, account_id: AccountId, character: Option<String>, score: i32) -> bool {
+        self.submit_score(account_id, character, DEFAULT_BOARD_ID.to_string(), score, None)
+    }
+}
+
+#[near_bindgen]
+impl ScoreHistory for ProfileState {
+    fn submit_score(&mut self, account_id: AccountId, character: Option<String>, board_id: String, score: i32, replay: Option<ReplayProof>) -> bool {
+        self.only_reporter();
+        self.assert_not_commit_reveal(&board_id);
+        self.record_score(account_id, character, board_id, score, replay)
+    }
+
+    fn get_score_history(&self, account_id: AccountId, character: Option<String>, from: u64, limit: u64) -> Vec<ScoreRecord> {
+        let character = character.unwrap_or_else(|| account_id.to_string());
+        match self.score_histories.get(&history_key(&env::sha256(account_id.as_bytes()), &character)) {
+            Some(history) => history.recent().into_iter().skip(from as usize).take(limit as usize).collect(),
+            None => vec![]
+        }
+    }
+
+    fn get_personal_bests(&self, account_id: AccountId, character: Option<String>) -> HashMap<String, i32> {
+        let character = character.unwrap_or_else(|| account_id.to_string());
+        self.score_histories.get(&history_key(&env::sha256(account_id.as_bytes()), &character))
+            .map(|history| history.boards.into_iter().map(|(board_id, stats)| (board_id, stats.best.score)).collect())
+            .unwrap_or_default()
+    }
+
+    fn get_score_improvement(&self, account_id: AccountId, character: Option<String>, board_id: String) -> Option<ScoreImprovement> {
+        let character = character.unwrap_or_else(|| account_id.to_string());
+        let history = self.score_histories.get(&history_key(&env::sha256(account_id.as_bytes()), &character))?;
+        let stats = history.boards.get(&board_id)?;
+        Some(ScoreImprovement {
+            improvement: stats.best.score as i64 - stats.first.score as i64,
+            first: stats.first.clone(),
+            best: stats.best.clone(),
+            latest: stats.latest.clone(),
+            submissions: stats.submissions,
+        })
+    }
+
+    fn get_board_leaderboard(&self, board_id: String, from: u64, limit: u64) -> Vec<LeaderBoardEntry> {
+        let ranks = match self.board_ranks.get(&board_id) {
+            Some(ranks) => ranks,
+            None => return vec![]
+        };
+        ranks.iter()
+            .filter(|(_, account_id, _)| self.is_publicly_ranked(account_id))
+            .skip(from as usize)
+            .take(limit as usize)
+            .map(|(score, account_id, character)| self.leaderboard_entry(character, Profile { username: account_id, score, hourly_reward_at: 0 }))
+            .collect()
+    }
+}
+
+#[near_bindgen]
+impl ScoreAttestations for ProfileState {
+    fn submit_attested_score(&mut self, attestation: ScoreAttestation, character: Option<String>, signature: Base64VecU8) -> bool {
+        self.use_attestation(&attestation, &signature);
+        self.assert_not_commit_reveal(&attestation.board_id);
+        self.record_score(attestation.account_id, character, attestation.board_id, attestation.score, None)
+    }
+
+    fn clean_used_nonces(&mut self, limit: u64) -> u64 {
+        // an expired attestation is rejected before its nonce is checked
+        let expired: Vec<(u64, Vec<u8>)> = self.nonce_expiries.iter()
+            .map(|(key, _)| key)
+            .take_while(|(expiry, _)| *expiry < env::block_timestamp())
+            .take(limit as usize)
+            .collect();
+        for key in &expired {
+            self.nonce_expiries.remove(key);
+            self.used_nonces.remove(&key.1);
+        }
+        expired.len() as u64
+    }
+}
+
+#[near_bindgen]
+impl CommitReveal for ProfileState {
+    fn commit_score(&mut self, board_id: String, character: Option<String>, hash: String) {
+        let rules = self.commit_reveal.get(&board_id).expect("The board doesn't use commit-reveal.");
+        assert!(env::block_timestamp() <= rules.commit_end.0, "The commit period is over.");
+        assert!(hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()), "Commit hash should be a hex encoded SHA-256.");
+        let account_id = env::predecessor_account_id();
+        let primary_account_id = self.get_primary_account(account_id.to_string());
+        assert!(self.check_access(primary_account_id.to_string()), "Only players with a profile can commit a score.");
+        // the first character is keyed by the primary account id, see `grant_access`
+        let character = character.unwrap_or(primary_account_id);
+        let key = commit_key(&account_id, &character, &board_id);
+        if let Some(commit) = self.score_commits.insert(&key, &ScoreCommit {
+            hash: hash.to_lowercase(),
+            board_id,
+            commit_end: rules.commit_end,
+            reveal_end: rules.reveal_end,
+        }) {
+            self.commit_expiries.remove(&(commit.reveal_end.0, key.to_vec()));
+        }
+        self.commit_expiries.insert(&(rules.reveal_end.0, key), &());
+    }
+
+    fn reveal_score(&mut self, attestation: ScoreAttestation, character: Option<String>, salt: String, signature: Base64VecU8, replay: Option<ReplayProof>) -> bool {
+        let board_id = attestation.board_id.to_string();
+        let score = attestation.score;
+        let rules = self.commit_reveal.get(&board_id).expect("The board doesn't use commit-reveal.");
+        assert!(env::block_timestamp() > rules.commit_end.0, "The reveal period has not started.");
+        assert!(env::block_timestamp() <= rules.reveal_end.0, "The reveal period is over.");
+        let account_id = env::predecessor_account_id();
+        let character = character.unwrap_or_else(|| self.get_primary_account(account_id.to_string()));
+        let key = commit_key(&account_id, &character, &board_id);
+        let commit = self.score_commits.get(&key).expect("No commit to reveal.");
+        assert_eq!(commit.commit_end, rules.commit_end, "The commit belongs to a previous round.");
+        let hash = env::sha256(&(score, &salt, &account_id).try_to_vec().unwrap());
+        let hash: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(commit.hash, hash, "The score doesn't match the commit.");
+        self.use_attestation(&attestation, &signature);
+
+        self.score_commits.remove(&key);
+        self.commit_expiries.remove(&(commit.reveal_end.0, key));
+        self.record_score(account_id, Some(character), board_id, score, replay)
+    }
+
+    fn clean_expired_commits(&mut self, limit: u64) -> u64 {
+        let now = env::block_timestamp();
+        let expired: Vec<(u64, Vec<u8>)> = self.commit_expiries.iter()
+            .map(|(key, _)| key)
+            .take_while(|(reveal_end, _)| *reveal_end < now)
+            .take(limit as usize)
+            .collect();
+        let mut removed = 0;
+        for (reveal_end, key) in expired {
+            self.commit_expiries.remove(&(reveal_end, key.to_vec()));
+            let mut commit = self.score_commits.get(&key).unwrap();
+            match self.commit_reveal.get(&commit.board_id) {
+                // the reveal deadline of the round was moved back
+                Some(rules) if rules.commit_end == commit.commit_end && now <= rules.reveal_end.0 => {
+                    commit.reveal_end = rules.reveal_end;
+                    self.score_commits.insert(&key, &commit);
+                    self.commit_expiries.insert(&(rules.reveal_end.0, key), &());
+                }
+                _ => {
+                    self.score_commits.remove(&key);
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
+
+    fn get_score_commit(&self, account_id: AccountId, character: Option<String>, board_id: String) -> Option<ScoreCommit> {
+        let character = character.unwrap_or_else(|| account_id.to_string());
+        self.score_commits.get(&commit_key(&account_id, &character, &board_id))
+    }
+}
+
+#[near_bindgen]
+impl ScoreChallenges for ProfileState {
+    #[payable]
+    fn dispute_score(&mut self, score_id: u64, reason: String) {
+        let mut provisional = self.provisional_scores.get(&score_id).expect("Provisional score not found.");
+        assert!(provisional.dispute.is_none(), "The score is already disputed.");
+        assert!(env::block_timestamp() - provisional.submitted_at <= self.challenge_rules.window, "The challenge window is over.");
+        assert!(reason.len() <= MAX_DISPUTE_REASON_LENGTH, "Dispute reasons should have at most {} bytes.", MAX_DISPUTE_REASON_LENGTH);
+        assert!(env::attached_deposit() >= self.challenge_rules.bond.0, "Disputing a score requires a bond of {} yoctoNEAR.", self.challenge_rules.bond.0);
+
+        let dispute = ScoreDispute {
+            challenger_id: env::predecessor_account_id(),
+            bond: U128(env::attached_deposit()),
+            reason,
+        };
+        log_event("score_disputed", json!({
+            "score_id": score_id,
+            "challenger_id": dispute.challenger_id,
+            "bond": dispute.bond,
+            "reason": dispute.reason,
+        }));
+        provisional.dispute = Some(dispute);
+        self.provisional_scores.insert(&score_id, &provisional);
+    }
+
+    fn resolve_dispute(&mut self, score_id: u64, remove: bool) {
+        self.only_moderator();
+        let provisional = self.provisional_scores.get(&score_id).expect("Provisional score not found.");
+        let dispute = provisional.dispute.as_ref().expect("The score is not disputed.");
+        let (challenger_id, bond) = (dispute.challenger_id.to_string(), dispute.bond.0);
+        self.remove_provisional(score_id, &provisional);
+        if remove {
+            // the score was never saved, it leaves no trace in the history
+            Promise::new(challenger_id).transfer(bond);
+        } else {
+            // the bond is slashed, it stays with the contract
+            self.confirm_score(&provisional.account_id, &provisional.character, provisional.record(), true);
+        }
+        log_event("score_dispute_resolved", json!({
+            "score_id": score_id,
+            "moderator_id": env::predecessor_account_id(),
+            "removed": remove,
+        }));
+    }
+
+    fn finalize_score(&mut self, score_id: u64) -> bool {
+        let provisional = self.provisional_scores.get(&score_id).expect("Provisional score not found.");
+        assert!(provisional.dispute.is_none(), "The score is disputed, see `resolve_dispute`.");
+        assert!(env::block_timestamp() - provisional.submitted_at > self.challenge_rules.window, "The challenge window is not over.");
+        self.remove_provisional(score_id, &provisional);
+        self.confirm_score(&provisional.account_id, &provisional.character, provisional.record(), true)
+    }
+
+    fn get_provisional_score(&self, score_id: u64) -> Option<ProvisionalScore> {
+        self.provisional_scores.get(&score_id)
+    }
+
+    fn get_provisional_scores(&self, from: u64, limit: u64) -> Vec<(u64, ProvisionalScore)> {
+        self.provisional_scores.iter().skip(from as usize).take(limit as usize).collect()
+    }
+}
+
+#[near_bindgen]
+impl ScoreReviews for ProfileState {
+    fn approve_score(&mut self, review_id: u64) -> bool {
+        self.only_moderator();
+        let review = self.pending_reviews.remove(&review_id).expect("Pending review not found.");
+        log_event("score_reviewed", json!({
+            "review_id": review_id,
+            "moderator_id": env::predecessor_account_id(),
+            "approved": true,
+        }));
+        let ranked_best = self.ranked_best(&review.account_id, &review.character, &review.board_id);
+        let record = ScoreRecord {
+            score: review.score,
+            board_id: review.board_id,
+            timestamp: review.submitted_at,
+            replay: review.replay,
+        };
+        self.rank_score(review.account_id, review.character, record, ranked_best)
+    }
+
+    fn reject_score(&mut self, review_id: u64) {
+        self.only_moderator();
+        self.pending_reviews.remove(&review_id).expect("Pending review not found.");
+        log_event("score_reviewed", json!({
+            "review_id": review_id,
+            "moderator_id": env::predecessor_account_id(),
+            "approved": false,
+        }));
+    }
+
+    fn get_pending_review(&self, review_id: u64) -> Option<PendingReview> {
+        self.pending_reviews.get(&review_id)
+    }
+
+    fn get_pending_reviews(&self, from: u64, limit: u64) -> Vec<(u64, PendingReview)> {
+        self.pending_reviews.iter().skip(from as usize).take(limit as usize).collect()
+    }
+
+    fn get_board_distribution(&self, board_id: String) -> Option<BoardDistribution> {
+        self.board_distributions.get(&board_id)
+    }
+}
+
+#[near_bindgen]
+impl ProfileTransfer for ProfileState {
+    fn start_profile_transfer(&mut self, new_account_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can transfer it.");
+        assert!(env::is_valid_account_id(new_account_id.as_bytes()), "New account ID is invalid.");
+        assert_ne!(account_id, new_account_id, "The profile can not be transferred to its own account.");
+        assert!(!self.check_access(new_account_id.to_string()), "The new account already has a profile.");
+        assert!(self.get_linked_accounts(account_id.to_string()).is_empty(), "Unlink the linked accounts before transferring the profile.");
+        assert_eq!(self.get_primary_account(new_account_id.to_string()), new_account_id, "The new account is linked to another profile.");
+        self.profile_transfers.insert(&env::sha256(account_id.as_bytes()), &new_account_id);
+        log_event("profile_transfer_started", json!({ "old_account_id": account_id, "new_account_id": new_account_id }));
+    }
+
+    fn cancel_profile_transfer(&mut self) {
+        let account_id = env::predecessor_account_id();
+        if let Some(new_account_id) = self.profile_transfers.remove(&env::sha256(account_id.as_bytes())) {
+            log_event("profile_transfer_cancelled", json!({ "old_account_id": account_id, "new_account_id": new_account_id }));
+        }
+    }
+
+    fn accept_profile_transfer(&mut self, old_account_id: AccountId) {
+        let new_account_id = env::predecessor_account_id();
+        let old_hash = env::sha256(old_account_id.as_bytes());
+        let new_hash = env::sha256(new_account_id.as_bytes());
+        assert_eq!(self.profile_transfers.get(&old_hash), Some(new_account_id.to_string()), "No profile transfer to accept.");
+        assert!(!self.check_access(new_account_id.to_string()), "The new account already has a profile.");
+        // either account may have been linked since the transfer started
+        assert!(self.get_linked_accounts(old_account_id.to_string()).is_empty(), "Unlink the linked accounts before transferring the profile.");
+        assert_eq!(self.get_primary_account(new_account_id.to_string()), new_account_id, "The new account is linked to another profile.");
+        self.assert_no_pending_scores(&old_account_id);
+        self.profile_transfers.remove(&old_hash);
+
+        // the characters are stored under the account, they are copied to the new one
+        let mut old_profiles = self.state.remove(&old_hash).unwrap();
+        let mut new_profiles: UnorderedMap<String, Profile> = UnorderedMap::new(profiles_prefix(&new_hash));
+        for (character, mut profile) in old_profiles.to_vec() {
+            profile.username = new_account_id.to_string();
+            let new_character = if character == old_account_id { new_account_id.to_string() } else { character.to_string() };
+            new_profiles.insert(&new_character, &profile);
+            if let Some(history) = self.score_histories.get(&history_key(&old_hash, &character)) {
+                self.move_board_ranks(&old_account_id, &character, &history, Some((&new_account_id, &new_character)));
+            }
+            move_value(&mut self.score_histories, &history_key(&old_hash, &character), &history_key(&new_hash, &new_character));
+        }
+        old_profiles.clear();
+        self.state.insert(&new_hash, &new_profiles);
+        // the submissions are only counted on the boards with rules
+        for board_id in self.board_rules.keys() {
+            move_value(&mut self.submission_windows, &board_key(&old_hash, &board_id), &board_key(&new_hash, &board_id));
+            move_value(&mut self.rejection_counts, &board_key(&old_hash, &board_id), &board_key(&new_hash, &board_id));
+        }
+
+        if let Some(identity) = self.get_identity(&old_hash) {
+            self.remove_identity(&old_hash);
+            self.save_identity(&new_hash, &identity);
+        }
+        if let Some(display_name) = self.display_names.remove(&old_hash) {
+            self.name_owners.insert(&display_name.key, &new_account_id);
+            self.display_names.insert(&new_hash, &display_name);
+        }
+        move_value(&mut self.profile_editors, &old_hash, &new_hash);
+        move_value(&mut self.attributes, &old_hash, &new_hash);
+        move_value(&mut self.avatars, &old_hash, &new_hash);
+        move_value(&mut self.equipped_items, &old_hash, &new_hash);
+        move_value(&mut self.privacy, &old_hash, &new_hash);
+        move_value(&mut self.friends, &old_hash, &new_hash);
+        for friend_id in self.get_friends(new_account_id.to_string()) {
+            let friend_hash = env::sha256(friend_id.as_bytes());
+            remove_from_list(&mut self.friends, &friend_hash, &old_account_id);
+            let mut friends = self.friends.get(&friend_hash).unwrap_or_default();
+            friends.push(new_account_id.to_string());
+            self.friends.insert(&friend_hash, &friends);
+        }
+        self.friend_requests.remove(&old_hash);
+        if let Some(region) = self.player_regions.remove(&old_hash) {
+            self.leave_region(&old_account_id, &region);
+            self.join_region(&new_account_id, &region);
+            self.player_regions.insert(&new_hash, &region);
+        }
+        if let Some(guild_id) = self.member_guilds.remove(&old_hash) {
+            let mut guild = self.guilds.get(&guild_id).unwrap();
+            for member in guild.members.iter_mut().filter(|member| member.account_id == old_account_id) {
+                member.account_id = new_account_id.to_string();
+            }
+            if guild.deposit_owner == old_account_id {
+                guild.deposit_owner = new_account_id.to_string();
+            }
+            self.guilds.insert(&guild_id, &guild);
+            self.member_guilds.insert(&new_hash, &guild_id);
+        }
+        log_event("profile_transfer", json!({ "old_account_id": old_account_id, "new_account_id": new_account_id }));
+    }
+
+    fn get_profile_transfer(&self, account_id: AccountId) -> Option<AccountId> {
+        self.profile_transfers.get(&env::sha256(account_id.as_bytes()))
+    }
+}
+
+#[near_bindgen]
+impl LinkedAccounts for ProfileState {
+    fn propose_link(&mut self, linked_account_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can link accounts.");
+        assert!(env::is_valid_account_id(linked_account_id.as_bytes()), "Linked account ID is invalid.");
+        assert_ne!(account_id, linked_account_id, "An account can not be linked to itself.");
+        self.link_proposals.insert(&env::sha256(linked_account_id.as_bytes()), &account_id);
+        log_event("account_link_proposed", json!({ "primary_account_id": account_id, "linked_account_id": linked_account_id }));
+    }
+
+    fn confirm_link(&mut self, primary_account_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        let account_hash = env::sha256(account_id.as_bytes());
+        let primary_hash = env::sha256(primary_account_id.as_bytes());
+        assert_eq!(self.link_proposals.get(&account_hash), Some(primary_account_id.to_string()), "No link to confirm.");
+        assert!(!self.check_access(account_id.to_string()), "Accounts with a profile can not be linked.");
+        assert!(self.get_linked_accounts(account_id.to_string()).is_empty(), "Accounts with linked accounts can not be linked.");
+        // the primary account may have been linked or revoked since the proposal
+        assert!(self.check_access(primary_account_id.to_string()), "The primary account has no profile.");
+        let mut linked_accounts = self.linked_accounts.get(&primary_hash).unwrap_or_default();
+        assert!(linked_accounts.len() < MAX_LINKED_ACCOUNTS, "A profile can not have more than {} linked accounts.", MAX_LINKED_ACCOUNTS);
+
+        self.link_proposals.remove(&account_hash);
+        self.unlink_account(&account_id);
+        linked_accounts.push(account_id.to_string());
+        self.linked_accounts.insert(&primary_hash, &linked_accounts);
+        self.primary_accounts.insert(&account_hash, &primary_account_id);
+        log_event("account_linked", json!({ "primary_account_id": primary_account_id, "linked_account_id": account_id }));
+    }
+
+    fn unlink(&mut self, account_id: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        let primary_account_id = self.get_primary_account(account_id.to_string());
+        assert!(predecessor == account_id || predecessor == primary_account_id, "Only the linked or the primary account can unlink.");
+        assert_ne!(primary_account_id, account_id, "The account is not linked.");
+        self.unlink_account(&account_id);
+    }
+
+    fn get_primary_account(&self, account_id: AccountId) -> AccountId {
+        self.primary_accounts.get(&env::sha256(account_id.as_bytes())).unwrap_or(account_id)
+    }
+
+    fn get_linked_accounts(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.linked_accounts.get(&env::sha256(account_id.as_bytes())).unwrap_or_default()
+    }
+}
+
+#[near_bindgen]
+impl Friends for ProfileState {
+    fn send_friend_request(&mut self, account_id: AccountId) {
+        let sender_id = env::predecessor_account_id();
+        assert!(self.check_access(sender_id.to_string()), "Only players with a profile can have friends.");
+        assert!(self.check_access(account_id.to_string()), "Friends should have a profile.");
+        assert_ne!(sender_id, account_id, "A player can not be its own friend.");
+        if self.get_friends(sender_id.to_string()).contains(&account_id) {
+            return;
+        }
+        if self.get_friend_requests(sender_id.to_string()).contains(&account_id) {
+            return self.accept_friend_request(account_id);
+        }
+
+        let account_hash = env::sha256(account_id.as_bytes());
+        let mut requests = self.friend_requests.get(&account_hash).unwrap_or_default();
+        if !requests.contains(&sender_id) {
+            assert!(requests.len() < MAX_FRIEND_REQUESTS, "{} has too many pending friend requests.", account_id);
+            requests.push(sender_id);
+            self.friend_requests.insert(&account_hash, &requests);
+        }
+    }
+
+    fn accept_friend_request(&mut self, account_id: AccountId) {
+        let receiver_id = env::predecessor_account_id();
+        assert!(
+            remove_from_list(&mut self.friend_requests, &env::sha256(receiver_id.as_bytes()), &account_id),
+            "No friend request to accept."
+        );
+        // the sender may have revoked its profile since
+        assert!(self.check_access(account_id.to_string()), "Friends should have a profile.");
+        self.add_friend(&receiver_id, &account_id);
+        self.add_friend(&account_id, &receiver_id);
+        env::log(format!("{} and {} are now friends", receiver_id, account_id).as_bytes());
+    }
+
+    fn decline_friend_request(&mut self, account_id: AccountId) {
+        assert!(
+            remove_from_list(&mut self.friend_requests, &env::sha256(env::predecessor_account_id().as_bytes()), &account_id),
+            "No friend request to decline."
+        );
+    }
+
+    fn remove_friend(&mut self, account_id: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert!(remove_from_list(&mut self.friends, &env::sha256(predecessor.as_bytes()), &account_id), "Not a friend.");
+        remove_from_list(&mut self.friends, &env::sha256(account_id.as_bytes()), &predecessor);
+    }
+
+    fn get_friends(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.friends.get(&env::sha256(account_id.as_bytes())).unwrap_or_default()
+    }
+
+    fn get_friend_requests(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.friend_requests.get(&env::sha256(account_id.as_bytes())).unwrap_or_default()
+    }
+
+    fn get_friends_leaderboard(&self, account_id: AccountId, from: u64, limit: u64) -> Vec<LeaderBoardEntry> {
+        self.friends_leaderboard(account_id, None, from, limit)
+    }
+
+    fn get_friends_leaderboard_as_caller(&mut self, from: u64, limit: u64) -> Vec<LeaderBoardEntry> {
+        let account_id = env::predecessor_account_id();
+        self.friends_leaderboard(account_id.to_string(), Some(&account_id), from, limit)
+    }
+}
+
+#[near_bindgen]
+impl Guilds for ProfileState {
+    #[payable]
+    fn create_guild(&mut self, name: String) -> u64 {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can create a guild.");
+        assert!(env::attached_deposit() >= self.guild_deposit, "Creating a guild requires a deposit of {} yoctoNEAR.", self.guild_deposit);
+        let name = normalize_display_name(&name);
+        let key = display_name_key(&name);
+        assert!(self.guild_names.get(&key).is_none(), "Guild name is already taken.");
+
+        let guild_id = self.next_guild_id;
+        self.next_guild_id += 1;
+        let mut guild = Guild {
+            name,
+            members: vec![],
+            invites: vec![],
+            join_requests: vec![],
+            deposit: U128(env::attached_deposit()),
+            deposit_owner: account_id.to_string(),
+            treasury: 0,
+            tithe_percent: 0,
+            quorum_percent: DEFAULT_GUILD_QUORUM_PERCENT,
+        };
+        self.add_guild_member(guild_id, &mut guild, &account_id);
+        guild.members[0].role = GuildRole::Founder;
+        self.guilds.insert(&guild_id, &guild);
+        self.guild_names.insert(&key, &guild_id);
+        env::log(format!("{} founded guild {} {}", account_id, guild_id, guild.name).as_bytes());
+        guild_id
+    }
+
+    fn invite_to_guild(&mut self, account_id: AccountId) {
+        let (guild_id, mut guild, role) = self.caller_guild();
+        assert_ne!(role, GuildRole::Member, "Only guild officers can invite players.");
+        assert!(self.check_access(account_id.to_string()), "Guild members should have a profile.");
+        if !guild.invites.contains(&account_id) {
+            assert!(guild.invites.len() < MAX_GUILD_REQUESTS, "The guild has too many pending invites.");
+            guild.invites.push(account_id);
+            self.guilds.insert(&guild_id, &guild);
+        }
+    }
+
+    fn accept_guild_invite(&mut self, guild_id: u64) {
+        let account_id = env::predecessor_account_id();
+        let mut guild = self.guilds.get(&guild_id).expect("Guild not found.");
+        assert!(guild.invites.contains(&account_id), "No guild invite to accept.");
+        // the player may have revoked its profile since
+        assert!(self.check_access(account_id.to_string()), "Guild members should have a profile.");
+        self.add_guild_member(guild_id, &mut guild, &account_id);
+    }
+
+    fn request_to_join_guild(&mut self, guild_id: u64) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Guild members should have a profile.");
+        let mut guild = self.guilds.get(&guild_id).expect("Guild not found.");
+        if !guild.join_requests.contains(&account_id) {
+            assert!(guild.join_requests.len() < MAX_GUILD_REQUESTS, "The guild has too many pending join requests.");
+            guild.join_requests.push(account_id);
+            self.guilds.insert(&guild_id, &guild);
+        }
+    }
+
+    fn accept_join_request(&mut self, account_id: AccountId) {
+        let (guild_id, mut guild, role) = self.caller_guild();
+        assert_ne!(role, GuildRole::Member, "Only guild officers can accept join requests.");
+        assert!(guild.join_requests.contains(&account_id), "No join request to accept.");
+        // the player may have revoked its profile since
+        assert!(self.check_access(account_id.to_string()), "Guild members should have a profile.");
+        self.add_guild_member(guild_id, &mut guild, &account_id);
+    }
+
+    fn leave_guild(&mut self) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.member_guilds.get(&env::sha256(account_id.as_bytes())).is_some(), "Not in a guild.");
+        self.remove_guild_member(&account_id);
+    }
+
+    fn kick_from_guild(&mut self, account_id: AccountId) {
+        let (guild_id, guild, role) = self.caller_guild();
+        let member = guild.member(&account_id).expect("Not a member of the guild.");
+        let can_kick = match member.role {
+            GuildRole::Member => role != GuildRole::Member,
+            GuildRole::Officer => role == GuildRole::Founder,
+            GuildRole::Founder => false,
+        };
+        assert!(can_kick, "Not allowed to kick this member.");
+        self.remove_guild_member(&account_id);
+        env::log(format!("{} was kicked from guild {}", account_id, guild_id).as_bytes());
+    }
+
+    fn set_guild_officer(&mut self, account_id: AccountId, is_officer: bool) {
+        let (guild_id, mut guild, role) = self.caller_guild();
+        assert_eq!(role, GuildRole::Founder, "Only the guild founder can change officers.");
+        let member = guild.members.iter_mut()
+            .find(|member| member.account_id == account_id && member.role != GuildRole::Founder)
+            .expect("Not a member of the guild.");
+        member.role = if is_officer { GuildRole::Officer } else { GuildRole::Member };
+        self.guilds.insert(&guild_id, &guild);
+    }
+
+    fn get_guild(&self, guild_id: u64) -> Option<Guild> {
+        self.guilds.get(&guild_id)
+    }
+
+    fn get_guild_of(&self, account_id: AccountId) -> Option<u64> {
+        self.member_guilds.get(&env::sha256(account_id.as_bytes()))
+    }
+
+    fn get_guild_leaderboard(&self, from: u64, limit: u64) -> Vec<GuildEntry> {
+        let mut entries: Vec<GuildEntry> = self.guilds.iter().map(|(guild_id, guild)| GuildEntry {
+            guild_id,
+            score: guild.score(self.guild_score_rule),
+            members: guild.members.len() as u64,
+            name: guild.name,
+        }).collect();
+        entries.sort_by(|a, b| b.score.cmp(&a.score).then(a.guild_id.cmp(&b.guild_id)));
+        entries.into_iter().skip(from as usize).take(limit as usize).collect()
+    }
+}
+
+#[near_bindgen]
+impl GuildTreasury for ProfileState {
+    fn set_guild_treasury_rules(&mut self, tithe_percent: u8, quorum_percent: u8) {
+        let (guild_id, mut guild, role) = self.caller_guild();
+        assert_eq!(role, GuildRole::Founder, "Only the guild founder can change the treasury rules.");
+        assert!(tithe_percent <= MAX_GUILD_TITHE_PERCENT, "The tithe can not be more than {} percent.", MAX_GUILD_TITHE_PERCENT);
+        assert!(0 < quorum_percent && quorum_percent <= 100, "The quorum should be between 1 and 100 percent.");
+        guild.tithe_percent = tithe_percent;
+        guild.quorum_percent = quorum_percent;
+        self.guilds.insert(&guild_id, &guild);
+    }
+
+    fn propose_treasury_spend(&mut self, receiver_id: AccountId, amount: u64, description: String) -> u64 {
+        let (guild_id, guild, role) = self.caller_guild();
+        assert_ne!(role, GuildRole::Member, "Only guild officers can propose to spend the treasury.");
+        assert!(env::is_valid_account_id(receiver_id.as_bytes()), "Receiver's account ID is invalid.");
+        assert!(0 < amount && amount <= guild.treasury, "The treasury has {} reward points.", guild.treasury);
+        assert!(description.chars().count() <= MAX_PROPOSAL_DESCRIPTION_LENGTH, "Descriptions can not be longer than {} characters.", MAX_PROPOSAL_DESCRIPTION_LENGTH);
+
+        let proposal_id = self.next_proposal_id;
+        self.next_proposal_id += 1;
+        self.treasury_proposals.insert(&proposal_id, &TreasuryProposal {
+            guild_id,
+            proposer_id: env::predecessor_account_id(),
+            receiver_id,
+            amount,
+            description,
+            votes: vec![],
+            status: ProposalStatus::Open,
+            created_at: env::block_timestamp(),
+        });
+        proposal_id
+    }
+
+    fn vote_treasury_spend(&mut self, proposal_id: u64, approve: bool) {
+        let account_id = env::predecessor_account_id();
+        let mut proposal = self.treasury_proposals.get(&proposal_id).expect("Proposal not found.");
+        let (guild_id, mut guild, _) = self.caller_guild();
+        assert_eq!(guild_id, proposal.guild_id, "Only members of the guild can vote.");
+        assert_eq!(proposal.status, ProposalStatus::Open, "The proposal is closed.");
+        assert!(env::block_timestamp() < proposal.created_at + TREASURY_PROPOSAL_DURATION, "The proposal has expired.");
+        assert!(proposal.votes.iter().all(|(voter_id, _)| voter_id != &account_id), "Already voted.");
+        proposal.votes.push((account_id, approve));
+
+        // only the votes of the current members count
+        let members = guild.members.len() as u64;
+        let quorum = (members * guild.quorum_percent as u64).div_ceil(100);
+        let (approvals, rejections) = proposal.votes.iter()
+            .filter(|(voter_id, _)| guild.member(voter_id).is_some())
+            .fold((0, 0), |(approvals, rejections), (_, approve)| if *approve { (approvals + 1, rejections) } else { (approvals, rejections + 1) });
+        if approvals >= quorum {
+            if guild.treasury >= proposal.amount {
+                guild.treasury -= proposal.amount;
+                proposal.status = ProposalStatus::Executed;
+                self.guilds.insert(&guild_id, &guild);
+                log_event("guild_treasury_debit", json!({
+                    "guild_id": guild_id,
+                    "proposal_id": proposal_id,
+                    "receiver_id": proposal.receiver_id,
+                    "amount": proposal.amount,
+                    "balance": guild.treasury,
+                }));
+            } else {
+                proposal.status = ProposalStatus::Failed;
+            }
+        } else if members - rejections < quorum {
+            proposal.status = ProposalStatus::Rejected;
+        }
+        self.treasury_proposals.insert(&proposal_id, &proposal);
+    }
+
+    fn get_treasury_proposal(&self, proposal_id: u64) -> Option<TreasuryProposal> {
+        self.treasury_proposals.get(&proposal_id)
+    }
+}
+
+#[near_bindgen]
+impl Regions for ProfileState {
+    fn set_region(&mut self, region: String) {
+        let account_id = env::predecessor_account_id();
+        let account_hash = env::sha256(account_id.as_bytes());
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can set a region.");
+        let code = region.trim().to_uppercase();
+        assert!(REGION_CODES.binary_search(&code.as_str()).is_ok(), "Unknown region {}, use an ISO 3166-1 alpha-2 code.", region);
+
+        let season = self.seasons.len();
+        let mut changes = 0;
+        if let Some(current) = self.player_regions.get(&account_hash) {
+            if current.code == code {
+                return;
+            }
+            if current.season == season {
+                changes = current.changes;
+            }
+            assert!(changes < MAX_REGION_CHANGES_PER_SEASON, "The region can not be changed more than {} times per season.", MAX_REGION_CHANGES_PER_SEASON);
+            self.leave_region(&account_id, &current);
+        }
+
+        let region = PlayerRegion {
+            code,
+            season,
+            changes: changes + 1,
+            best_score: self.best_score(&account_hash),
+        };
+        self.join_region(&account_id, &region);
+        self.player_regions.insert(&account_hash, &region);
+    }
+
+    fn get_region(&self, account_id: AccountId) -> Option<String> {
+        self.player_regions.get(&env::sha256(account_id.as_bytes())).map(|region| region.code)
+    }
+
+    fn get_top_players_by_region(&self, region: String, from: u64, limit: u64) -> Vec<LeaderBoardEntry> {
+        let ranks = match self.region_ranks.get(&region.to_uppercase()) {
+            Some(ranks) => ranks,
+            None => return vec![]
+        };
+        ranks.iter()
+            .filter(|(_, account_id, _)| self.is_publicly_ranked(account_id))
+            .skip(from as usize)
+            .take(limit as usize)
+            .map(|(score, account_id, character)| self.leaderboard_entry(character, Profile { username: account_id, score, hourly_reward_at: 0 }))
+            .collect()
+    }
+
+    fn get_region_leaderboard(&self, ranking: RegionRanking, from: u64, limit: u64) -> Vec<RegionEntry> {
+        let mut entries = self.region_stats.values_as_vector().to_vec();
+        match ranking {
+            RegionRanking::Total => entries.sort_by(|a, b| b.total_score.cmp(&a.total_score).then(a.region.cmp(&b.region))),
+            RegionRanking::Average => entries.sort_by(|a, b| b.average_score.cmp(&a.average_score).then(a.region.cmp(&b.region))),
+        }
+        entries.into_iter().skip(from as usize).take(limit as usize).collect()
+    }
+}
+
+#[near_bindgen]
+impl GameSessions for ProfileState {
+    #[payable]
+    fn start_session(&mut self, board_id: String) -> u64 {
+        let player_id = env::predecessor_account_id();
+        let account_id = self.get_primary_account(player_id.to_string());
+        assert!(self.check_access(account_id), "Only players with a profile can start a session.");
+        assert!(!board_id.is_empty() && board_id.len() <= MAX_BOARD_ID_LENGTH, "Board ids should have 1 to {} characters.", MAX_BOARD_ID_LENGTH);
+        assert!(env::attached_deposit() >= self.session_deposit, "Starting a session requires a deposit of {} yoctoNEAR.", self.session_deposit);
+
+        let session_id = self.next_session_id;
+        self.next_session_id += 1;
+        self.sessions.insert(&session_id, &GameSession {
+            player_id,
+            board_id: board_id.to_string(),
+            started_at: env::block_timestamp(),
+            submitted: false,
+            deposit: U128(env::attached_deposit()),
+        });
+        let mut stats = self.get_session_stats(board_id.to_string());
+        stats.started += 1;
+        stats.open += 1;
+        self.session_stats.insert(&board_id, &stats);
+        session_id
+    }
+
+    fn submit_session_score(&mut self, session_id: u64, attestation: ScoreAttestation, character: Option<String>, signature: Base64VecU8, replay: Option<ReplayProof>) -> bool {
+        let mut session = self.sessions.get(&session_id).expect("Session not found.");
+        assert_eq!(session.player_id, env::predecessor_account_id(), "Only the player of the session can submit its score.");
+        assert!(!session.submitted, "The score of the session was already submitted.");
+        let duration = env::block_timestamp() - session.started_at;
+        assert!(duration >= self.session_duration.0, "The session is too short.");
+        assert!(duration <= self.session_duration.1, "The session has expired.");
+        assert_eq!(attestation.board_id, session.board_id, "The attestation is for another board.");
+        self.use_attestation(&attestation, &signature);
+
+        session.submitted = true;
+        self.sessions.insert(&session_id, &session);
+        let mut stats = self.get_session_stats(session.board_id.to_string());
+        stats.submitted += 1;
+        self.session_stats.insert(&session.board_id, &stats);
+        self.assert_not_commit_reveal(&session.board_id);
+        self.record_score(session.player_id, character, session.board_id, attestation.score, replay)
+    }
+
+    fn end_session(&mut self, session_id: u64) {
+        let session = self.sessions.get(&session_id).expect("Session not found.");
+        assert_eq!(session.player_id, env::predecessor_account_id(), "Only the player of the session can end it.");
+        self.close_session(session_id, &session);
+    }
+
+    fn clean_expired_sessions(&mut self, limit: u64) -> u64 {
+        // sessions are started in the order of their ids, the expired ones come first
+        let mut ended = 0;
+        while ended < limit && self.oldest_session_id < self.next_session_id {
+            if let Some(session) = self.sessions.get(&self.oldest_session_id) {
+                if env::block_timestamp() - session.started_at <= self.session_duration.1 {
+                    break;
+                }
+                self.close_session(self.oldest_session_id, &session);
+                ended += 1;
+            }
+            self.oldest_session_id += 1;
+        }
+        ended
+    }
+
+    fn get_session(&self, session_id: u64) -> Option<GameSession> {
+        self.sessions.get(&session_id)
+    }
+
+    fn get_session_stats(&self, board_id: String) -> SessionStats {
+        self.session_stats.get(&board_id).unwrap_or_default()
+    }
+}
+
+#[near_bindgen]
+impl Characters for ProfileState {
+    fn create_character(&mut self, name: String) {
+        let account_id = env::predecessor_account_id();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut characters = self.state.get(&escrow_hash).expect("Only players with a profile can create characters.");
+        assert!(characters.len() < MAX_CHARACTERS, "A profile can not have more than {} characters.", MAX_CHARACTERS);
+        let name = normalize_display_name(&name);
+        assert!(characters.get(&name).is_none(), "Character {} already exists.", name);
+        characters.insert(&name, &Profile::new(account_id.to_string()));
+        self.state.insert(&escrow_hash, &characters);
+        self.rank_in_region(&escrow_hash, &account_id, &name, Some(0));
+        env::log(format!("{} created character {}", account_id, name).as_bytes());
+    }
+
+    fn rename_character(&mut self, name: String, new_name: String) {
+        let account_id = env::predecessor_account_id();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut characters = self.state.get(&escrow_hash).expect("Only players with a profile have characters.");
+        assert_ne!(name, account_id, "The default character can not be renamed.");
+        let new_name = normalize_display_name(&new_name);
+        assert!(characters.get(&new_name).is_none(), "Character {} already exists.", new_name);
+        let profile = characters.remove(&name).expect("Character not found.");
+        characters.insert(&new_name, &profile);
+        self.state.insert(&escrow_hash, &characters);
+        self.rank_in_region(&escrow_hash, &account_id, &name, None);
+        self.rank_in_region(&escrow_hash, &account_id, &new_name, Some(profile.score));
+        if let Some(history) = self.score_histories.get(&history_key(&escrow_hash, &name)) {
+            self.move_board_ranks(&account_id, &name, &history, Some((&account_id, &new_name)));
+        }
+        move_value(&mut self.score_histories, &history_key(&escrow_hash, &name), &history_key(&escrow_hash, &new_name));
+        env::log(format!("{} renamed character {} to {}", account_id, name, new_name).as_bytes());
+    }
+
+    fn delete_character(&mut self, name: String) {
+        let account_id = env::predecessor_account_id();
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut characters = self.state.get(&escrow_hash).expect("Only players with a profile have characters.");
+        assert_ne!(name, account_id, "The default character can not be deleted.");
+        assert!(characters.get(&name).is_some(), "Character not found.");
+        characters.remove(&name);
+        self.state.insert(&escrow_hash, &characters);
+        if let Some(history) = self.score_histories.remove(&history_key(&escrow_hash, &name)) {
+            self.move_board_ranks(&account_id, &name, &history, None);
+        }
+        self.refresh_guild_score(&account_id);
+        self.rank_in_region(&escrow_hash, &account_id, &name, None);
+        self.refresh_region_score(&escrow_hash);
+        env::log(format!("{} deleted character {}", account_id, name).as_bytes());
+    }
+
+    fn get_characters(&self, account_id: AccountId) -> Vec<(String, Profile)> {
+        match self.state.get(&env::sha256(account_id.as_bytes())) {
+            Some(characters) => characters.to_vec(),
+            None => vec![]
+        }
+    }
+}
+
+#[near_bindgen]
+impl PersonalInfo for ProfileState {
+    fn set_info(&mut self, account_id: AccountId, age: Option<u8>, f_name: Option<String>, l_name: Option<String>) -> bool {
+        if !self.can_edit_info(&account_id) {
+            return false;
+        }
+
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut identity = self.get_identity(&escrow_hash).unwrap_or_default();
+        if let Some(age) = age {
+            assert!(AGE_RANGE.0 <= age && age <= AGE_RANGE.1, "Age should be between {} and {}.", AGE_RANGE.0, AGE_RANGE.1);
+            identity.age = Some(age);
+        }
+
+        if let Some(f_name) = f_name {
+            identity.first_name = normalize_name(&f_name);
+        }
+
+        if let Some(l_name) = l_name {
+            identity.last_name = normalize_name(&l_name);
+        }
+
+        self.save_identity(&escrow_hash, &identity);
+        env::log("info was set".as_bytes());
+        true
+    }
+
+    fn clear_info(&mut self, account_id: AccountId, fields: Vec<InfoField>) -> bool {
+        if !self.can_edit_info(&account_id) {
+            return false;
+        }
+
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut identity = self.get_identity(&escrow_hash).unwrap_or_default();
+        for field in fields {
+            match field {
+                InfoField::FirstName => identity.first_name = String::new(),
+                InfoField::LastName => identity.last_name = String::new(),
+                InfoField::Age => identity.age = None,
+            }
+        }
+
+        self.save_identity(&escrow_hash, &identity);
+        env::log("info was cleared".as_bytes());
+        true
+    }
+
+    fn add_profile_editor(&mut self, editor_id: AccountId) {
+        assert!(env::is_valid_account_id(editor_id.as_bytes()), "Editor's account ID is invalid.");
+        let escrow_hash = env::sha256(env::predecessor_account_id().as_bytes());
+        let mut editors = self.profile_editors.get(&escrow_hash).unwrap_or_default();
+        if editors.contains(&editor_id) {
+            return;
+        }
+        assert!(editors.len() < MAX_PROFILE_EDITORS, "A profile can not have more than {} editors.", MAX_PROFILE_EDITORS);
+        editors.push(editor_id);
+        self.profile_editors.insert(&escrow_hash, &editors);
+    }
+
+    fn remove_profile_editor(&mut self, editor_id: AccountId) {
+        let escrow_hash = env::sha256(env::predecessor_account_id().as_bytes());
+        let mut editors = self.profile_editors.get(&escrow_hash).unwrap_or_default();
+        editors.retain(|editor| editor != &editor_id);
+        if editors.is_empty() {
+            self.profile_editors.remove(&escrow_hash);
+        } else {
+            self.profile_editors.insert(&escrow_hash, &editors);
+        }
+    }
+
+    fn get_profile_editors(&self, account_id: AccountId) -> Vec<AccountId> {
+        self.profile_editors.get(&env::sha256(account_id.as_bytes())).unwrap_or_default()
+    }
+
+    fn set_attributes(&mut self, account_id: AccountId, attributes: HashMap<String, String>) -> bool {
+        if !self.can_edit_info(&account_id) {
+            return false;
+        }
+
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut saved = self.get_attributes(&escrow_hash);
+        for (key, value) in attributes {
+            let schema = self.attribute_schema.get(&key).unwrap_or_else(|| env::panic(format!("Unknown attribute {}.", key).as_bytes()));
+            saved.insert(key.to_string(), schema.normalize(&key, &value));
+        }
+
+        self.save_attributes(&escrow_hash, &saved);
+        env::log("attributes were set".as_bytes());
+        true
+    }
+
+    fn remove_attributes(&mut self, account_id: AccountId, keys: Vec<String>) -> bool {
+        if !self.can_edit_info(&account_id) {
+            return false;
+        }
+
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        let mut saved = self.get_attributes(&escrow_hash);
+        for key in keys {
+            saved.remove(&key);
+        }
+
+        self.save_attributes(&escrow_hash, &saved);
+        env::log("attributes were removed".as_bytes());
+        true
+    }
+
+    fn get_info(&self, account_id: AccountId) -> Option<ProfileInfo> {
+        self.profile_info(account_id, None)
+    }
+
+    fn introduce(&self, account_id: AccountId) -> Option<(String, String, String)> {
+        if !self.can_view(&account_id, self.get_privacy(account_id.to_string()).info, None) {
+            env::log("info is private".as_bytes());
+            return None;
+        }
+        match self.get_identity(&env::sha256(account_id.as_bytes())) {
+            Some(identity) => Some(identity.introduce()),
+            None => {
+                env::log("not found to introduce".as_bytes());
+                None
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl Privacy for ProfileState {
+    fn set_privacy(&mut self, settings: PrivacySettings) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can set their privacy.");
+        let escrow_hash = env::sha256(account_id.as_bytes());
+        if settings == PrivacySettings::default() {
+            self.privacy.remove(&escrow_hash);
+        } else {
+            self.privacy.insert(&escrow_hash, &settings);
+        }
+    }
+
+    fn get_privacy(&self, account_id: AccountId) -> PrivacySettings {
+        self.privacy.get(&env::sha256(account_id.as_bytes())).unwrap_or_default()
+    }
+
+    fn get_info_as_caller(&mut self, account_id: AccountId) -> Option<ProfileInfo> {
+        self.profile_info(account_id, Some(&env::predecessor_account_id()))
+    }
+
+    fn get_own_rank(&mut self) -> Option<u64> {
+        let account_id = env::predecessor_account_id();
+        self.sorted_players().iter()
+            .position(|(_, profile)| profile.username == account_id)
+            .map(|index| index as u64 + 1)
+    }
+}
+
+#[near_bindgen]
+impl DisplayNames for ProfileState {
+    #[payable]
+    fn set_display_name(&mut self, name: String) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can set a display name.");
+        let name = normalize_display_name(&name);
+        let key = display_name_key(&name);
+        assert!(!self.reserved_names.contains(&key), "Display name is reserved.");
+        if let Some(owner_id) = self.name_owners.get(&key) {
+            assert_eq!(owner_id, account_id, "Display name is already taken.");
+        }
+
+        let deposit = env::attached_deposit();
+        let mut fee = 0;
+        if let Some(current) = self.display_names.get(&env::sha256(account_id.as_bytes())) {
+            if env::block_timestamp() < current.changed_at + self.rename_cooldown {
+                assert!(deposit >= self.rename_fee, "Renaming before the cooldown is over costs {} yoctoNEAR.", self.rename_fee);
+                fee = self.rename_fee;
+            }
+        }
+        if deposit > fee {
+            Promise::new(account_id.to_string()).transfer(deposit - fee);
+        }
+
+        env::log(format!("{} is now known as {}", account_id, name).as_bytes());
+        self.save_display_name(&account_id, name, key);
+    }
+
+    fn reserve_display_names(&mut self, names: Vec<String>) {
+        self.only_owner();
+        for name in names {
+            let key = display_name_key(&normalize_display_name(&name));
+            assert!(self.name_owners.get(&key).is_none(), "Display name {} is already taken.", name);
+            self.reserved_names.insert(&key);
+        }
+    }
+
+    fn release_display_name(&mut self, name: String) {
+        self.only_owner();
+        self.reserved_names.remove(&display_name_key(&collapse_name(&name)));
+    }
+
+    fn assign_display_name(&mut self, account_id: AccountId, name: String) {
+        self.only_owner();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can have a display name.");
+        let name = normalize_display_name(&name);
+        let key = display_name_key(&name);
+        assert!(self.reserved_names.remove(&key), "Only reserved display names can be assigned.");
+        env::log(format!("{} is now known as {}", account_id, name).as_bytes());
+        self.save_display_name(&account_id, name, key);
+    }
+
+    fn get_display_name(&self, account_id: AccountId) -> Option<String> {
+        self.display_names.get(&env::sha256(account_id.as_bytes())).map(|display_name| display_name.name)
+    }
+
+    fn get_account_by_display_name(&self, name: String) -> Option<AccountId> {
+        self.name_owners.get(&display_name_key(&collapse_name(&name)))
+    }
+}
+
+#[near_bindgen]
+impl Avatars for ProfileState {
+    fn set_avatar(&mut self, nft_contract_id: AccountId, token_id: u64) -> Promise {
+        let account_id = env::predecessor_account_id();
+        assert!(self.check_access(account_id.to_string()), "Only players with a profile can set an avatar.");
+        assert!(self.avatar_contracts.contains(&nft_contract_id), "NFT contract is not allowed for avatars.");
+        let avatar = Avatar { contract_id: nft_contract_id, token_id };
+        ext_avatar_nft::get_token_owner(token_id, &avatar.contract_id, 0, GAS_FOR_AVATAR_CALL)
+            .then(ext_self::on_avatar_owner(account_id, avatar, true, &env::current_account_id(), 0, GAS_FOR_AVATAR_CALLBACK))
+    }
+
+    fn refresh_avatar(&mut self, account_id: AccountId) -> Promise {
+        let avatar = self.get_avatar(account_id.to_string()).expect("No avatar set.");
+        ext_avatar_nft::get_token_owner(avatar.token_id, &avatar.contract_id, 0, GAS_FOR_AVATAR_CALL)
+            .then(ext_self::on_avatar_owner(account_id, avatar, false, &env::current_account_id(), 0, GAS_FOR_AVATAR_CALLBACK))
+    }
+
+    fn clear_avatar(&mut self) {
+        self.avatars.remove(&env::sha256(env::predecessor_account_id().as_bytes()));
+    }
+
+    fn get_avatar(&self, account_id: AccountId) -> Option<Avatar> {
+        self.avatars.get(&env::sha256(account_id.as_bytes()))
+    }
+}
+
+#[near_bindgen]
+impl SeasonPrizes for ProfileState {
+    #[payable]
+    fn finalize_season(&mut self) -> u64 {
+        self.only_owner();
+        let season_id = self.seasons.len();
+        let prize_pool = env::attached_deposit();
+        let mut standings = Vector::new(format!("season-{}", season_id).into_bytes());
+        let mut winners: Vec<SeasonWinner> = vec![];
+        let mut shares: Vec<u128> = vec![];
+        // one entry per account, with its best character
+        let mut ranked: HashSet<AccountId> = HashSet::new();
+        let players = self.public_players().into_iter().filter(|(_, profile)| ranked.insert(profile.get_username()));
+        for (index, (_, profile)) in players.enumerate() {
+            let rank = index as u64 + 1;
+            let username = profile.get_username();
+            standings.push(&(username.to_string(), profile.score));
+            if let Some((_, trophy, share)) = SEASON_PRIZE_TIERS.iter().find(|tier| rank <= tier.0) {
+                winners.push(SeasonWinner {
+                    account_id: username,
+                    rank,
+                    score: profile.score,
+                    trophy: trophy.to_string(),
+                    prize: U128(0),
+                    prize_status: PayoutStatus::Unpaid,
+                    trophy_status: PayoutStatus::Unpaid,
+                });
+                shares.push(*share);
+            }
+        }
+        assert!(prize_pool == 0 || !winners.is_empty(), "A season with a prize pool needs at least one ranked player.");
+
+        // the shares of the missing ranks go to the winners, the rounding remainder to the first one
+        let total_shares: u128 = shares.iter().sum();
+        for (winner, share) in winners.iter_mut().zip(shares) {
+            winner.prize = U128(prize_pool * share / total_shares);
+        }
+        let distributed: u128 = winners.iter().map(|winner| winner.prize.0).sum();
+        if let Some(first) = winners.first_mut() {
+            first.prize.0 += prize_pool - distributed;
+        }
+
+        self.seasons.push(&Season {
+            standings,
+            winners,
+            prize_pool,
+            finalized_at: env::block_timestamp(),
+        });
+        env::log(format!("season {} finalized with a prize pool of {}", season_id, prize_pool).as_bytes());
+        season_id
+    }
+
+    fn distribute_season_prizes(&mut self, season_id: u64, limit: u64) -> u64 {
+        let trophy_contract_id = self.trophy_contract_id.clone().expect("Trophy contract is not set.");
+        let mut season = self.seasons.get(season_id).expect("Season not found");
+        let batch = limit.min(MAX_SEASON_PAYOUT_BATCH);
+        let mut handled = 0;
+        for (index, winner) in season.winners.iter_mut().enumerate() {
+            if handled >= batch || env::prepaid_gas() - env::used_gas() < GAS_FOR_SEASON_PAYOUT {
+                break;
+            }
+            if winner.prize_status != PayoutStatus::Unpaid && winner.trophy_status != PayoutStatus::Unpaid {
+                continue;
+            }
+
+            if winner.prize_status == PayoutStatus::Unpaid {
+                winner.prize_status = PayoutStatus::Sending;
+                Promise::new(winner.account_id.to_string())
+                    .transfer(winner.prize.0)
+                    .then(ext_self::on_season_payout(season_id, index as u64, false, &env::current_account_id(), 0, GAS_FOR_SEASON_CALLBACK));
+            }
+            if winner.trophy_status == PayoutStatus::Unpaid {
+                winner.trophy_status = PayoutStatus::Sending;
+                let metadata = TrophyMetadata {
+                    title: format!("Season {} {} trophy", season_id, winner.trophy),
+                    description: format!("Rank {} with a score of {}", winner.rank, winner.score),
+                };
+                ext_trophy::mint_next(winner.account_id.to_string(), metadata, &trophy_contract_id, 0, GAS_FOR_TROPHY_MINT)
+                    .then(ext_self::on_season_payout(season_id, index as u64, true, &env::current_account_id(), 0, GAS_FOR_SEASON_CALLBACK));
+            }
+            handled += 1;
+        }
+
+        self.seasons.replace(season_id, &season);
+        handled
+    }
+
+    fn get_season_standings(&self, season_id: u64, from: u64, limit: u64) -> Vec<(String, i32)> {
+        match self.seasons.get(season_id) {
+            Some(season) => season.standings.iter()
+                .filter(|(username, _)| self.is_publicly_ranked(username))
+                .skip(from as usize)
+                .take(limit as usize)
+                .collect(),
+            None => vec![]
+        }
+    }
+
+    fn get_season_winners(&self, season_id: u64) -> Vec<SeasonWinner> {
+        match self.seasons.get(season_id) {
+            Some(season) => season.winners.into_iter()
+                .filter(|winner| self.is_publicly_ranked(&winner.account_id))
+                .collect(),
+            None => vec![]
+        }
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for ProfileState {
+    fn nft_on_transfer(&mut self, sender_id: AccountId, previous_owner_id: AccountId, token_id: u64, msg: String) -> PromiseOrValue<bool> {
+        let item_contract_id = self.item_contract_id.clone().expect("Item contract is not set.");
+        assert_eq!(env::predecessor_account_id(), item_contract_id, "Only the item contract can equip items.");
+        env::log(format!("item {} sent by {}: {}", token_id, sender_id, msg).as_bytes());
+        let account_hash = env::sha256(previous_owner_id.as_bytes());
+        if !self.check_access(previous_owner_id.to_string()) || self.equipped_items.get(&account_hash).is_some() {
+            env::log(format!("item {} can not be equipped by {}", token_id, previous_owner_id).as_bytes());
+            return PromiseOrValue::Value(true);
+        }
+
+        // the multiplier is read from the item contract, not trusted from `msg`
+        ext_items::get_token_metadata(token_id, &item_contract_id, 0, GAS_FOR_ITEM_CALL)
+            .then(ext_self::on_item_metadata(previous_owner_id, token_id, &env::current_account_id(), 0, GAS_FOR_ITEM_CALLBACK))
+            .into()
+    }
+}
+
+#[near_bindgen]
+impl EquippedItems for ProfileState {
+    fn unequip_item(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let item = self.equipped_items.remove(&env::sha256(account_id.as_bytes())).expect("No item equipped.");
+        ext_items::transfer(account_id.to_string(), item.token_id, &item.contract_id, 0, GAS_FOR_ITEM_CALL)
+            .then(ext_self::on_item_unequipped(account_id, item, &env::current_account_id(), 0, GAS_FOR_ITEM_CALLBACK))
+    }
+
+    fn get_equipped_item(&self, account_id: AccountId) -> Option<EquippedItem> {
+        self.equipped_items.get(&env::sha256(account_id.as_bytes()))
+    }
+
+    fn get_score_multiplier(&self, account_id: AccountId) -> u32 {
+        match self.get_equipped_item(account_id) {
+            Some(item) => item.multiplier.clamp(self.multiplier_bounds.0, self.multiplier_bounds.1),
+            None => MULTIPLIER_DENOMINATOR
+        }
+    }
+}
+
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+pub struct Profile {
+    username: String,
+    score: i32,
+    hourly_reward_at: i64
+}
+
+impl Profile {
+    #[init]
+    pub fn
+    new(account_id: String) -> Self {
+        Self {
+            score: 0,
+            username: account_id,
+            hourly_reward_at: 0,
+        }
+    }
+
+    pub fn
+    set_score(&mut self, score: i32) {
+        self.score = score;
+        env::log(format!("score now is {}.", self.score).as_bytes());
+    }
+
+    pub fn
+    get_score(&self) -> i32 {
+        env::log(format!("score now is {}.", self.score).as_bytes());
+        self.score
+    }
+
+    pub fn
+    get_username(&self) -> String {
+        return self.username.to_string()
+    }
+
+    pub fn
+    is_empty(&self) -> bool {
+        return self.username.is_empty()
+    }
+
+    fn
+    rewarded(&mut self, timestamp: i64) {
+        self.hourly_reward_at = timestamp;
+    }
+}
+
+impl Serialize for Profile {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        // 3 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("Profile", 2)?;
+        state.serialize_field("score", &self.score)?;
+        state.serialize_field("username", &self.username)?;
+        state.end()
+    }
+}
+
+impl Clone for Profile {
+    fn clone(&self) -> Self {
+        return Profile{
+            username: self.username.to_string(),
+            hourly_reward_at: self.hourly_reward_at,
+            score: self.score,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.score = source.score;
+        self.hourly_reward_at = source.hourly_reward_at;
+        self.username = source.username.to_string();
+    }
+}
+
+/// Personal info of an account, shared by all its profiles
+#[derive(Default, BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct Identity {
+    first_name: String,
+    last_name: String,
+    age: Option<u8>,
+}
+
+/// Profile of the `profile_v2` contract
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize)]
+pub struct LegacyIdentity {
+    first_name: String,
+    last_name: String,
+    age: i8,
+}
+
+impl From<LegacyIdentity> for Identity {
+    fn from(legacy: LegacyIdentity) -> Self {
+        let age = legacy.age as u8;
+        Self {
+            first_name: truncate_name(&collapse_name(&legacy.first_name)),
+            last_name: truncate_name(&collapse_name(&legacy.last_name)),
+            // 0 was "not set" and the age was never validated
+            age: if legacy.age > 0 && AGE_RANGE.0 <= age && age <= AGE_RANGE.1 { Some(age) } else { None },
+        }
+    }
+}
+
+/// Fields of the personal info which can be removed with `clear_info`
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum InfoField {
+    FirstName,
+    LastName,
+    Age,
+}
+
+impl Identity {
+    pub fn
+    introduce(&self) -> (String, String, String) {
+        let log_data = (
+            format!("My name is {}", self.first_name),
+            format!("Full name is {} {}", self.last_name, self.first_name),
+            match self.age {
+                Some(age) => format!("I'm {} years old", age),
+                None => "I'd rather not tell my age".to_string(),
+            },
+        );
+        env::log(format!("{:?}", log_data).as_bytes());
+        log_data
+    }
+}
+
+/// Identity and leaderboard stats of an account, returned by `get_info`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ProfileInfo {
+    account_id: AccountId,
+    first_name: String,
+    last_name: String,
+    age: Option<u8>,
+    attributes: HashMap<String, String>,
+    score: i32,
+    hourly_reward_at: i64,
+}
+
+/// Type and size limit of a profile attribute, see `set_attribute_schema`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AttributeSchema {
+    kind: AttributeKind,
+    // in characters, after normalization
+    max_length: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AttributeKind {
+    Text,
+    Integer,
+    Boolean,
+    Url,
+}
+
+impl AttributeSchema {
+    /// Value as stored, panics if it does not match the schema
+    fn normalize(&self, key: &str, value: &str) -> String {
+        let value = match self.kind {
+            AttributeKind::Text => {
+                let text = value.nfc().collect::<String>().trim().to_string();
+                assert!(!text.chars().any(|c| c.is_control() && c != '\n'), "Attribute {} can not contain control characters.", key);
+                text
+            }
+            AttributeKind::Integer => value.trim().parse::<i64>()
+                .unwrap_or_else(|_| env::panic(format!("Attribute {} should be an integer.", key).as_bytes()))
+                .to_string(),
+            AttributeKind::Boolean => value.trim().parse::<bool>()
+                .unwrap_or_else(|_| env::panic(format!("Attribute {} should be true or false.", key).as_bytes()))
+                .to_string(),
+            AttributeKind::Url => {
+                let url = value.trim().to_string();
+                assert!(url.starts_with("https://") && !url.chars().any(char::is_whitespace), "Attribute {} should be an https URL.", key);
+                url
+            }
+        };
+        assert!(!value.is_empty(), "Attribute {} can not be empty, remove it instead.", key);
+        assert!(value.chars().count() <= self.max_length as usize, "Attribute {} can not be longer than {} characters.", key, self.max_length);
+        value
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Guild {
+    name: String,
+    members: Vec<GuildMember>,
+    invites: Vec<AccountId>,
+    join_requests: Vec<AccountId>,
+    // paid by the founder, refunded to `deposit_owner` when the guild is disbanded
+    deposit: U128,
+    deposit_owner: AccountId,
+    // reward points, see `GuildTreasury`
+    treasury: u64,
+    tithe_percent: u8,
+    quorum_percent: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct TreasuryProposal {
+    guild_id: u64,
+    proposer_id: AccountId,
+    receiver_id: AccountId,
+    amount: u64,
+    description: String,
+    // (member, approves)
+    votes: Vec<(AccountId, bool)>,
+    status: ProposalStatus,
+    created_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum ProposalStatus {
+    Open,
+    Executed,
+    Rejected,
+    // approved while the treasury could not pay it
+    Failed,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct GuildMember {
+    account_id: AccountId,
+    role: GuildRole,
+    // best score among the characters of the member
+    best_score: i32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum GuildRole {
+    Founder,
+    Officer,
+    Member,
+}
+
+/// How the best scores of the members make the score of a guild
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum GuildScoreRule {
+    Sum,
+    // members missing to reach the count are counted as 0
+    AverageOfTop(u8),
+    Best,
+}
+
+/// Entry of `get_guild_leaderboard`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GuildEntry {
+    guild_id: u64,
+    name: String,
+    score: i64,
+    members: u64,
+}
+
+impl Guild {
+    fn member(&self, account_id: &str) -> Option<&GuildMember> {
+        self.members.iter().find(|member| member.account_id == account_id)
+    }
+
+    fn score(&self, rule: GuildScoreRule) -> i64 {
+        let mut scores: Vec<i64> = self.members.iter().map(|member| member.best_score as i64).collect();
+        match rule {
+            GuildScoreRule::Sum => scores.iter().sum(),
+            GuildScoreRule::AverageOfTop(count) => {
+                scores.sort_unstable_by(|a, b| b.cmp(a));
+                scores.iter().take(count as usize).sum::<i64>() / count as i64
+            }
+            GuildScoreRule::Best => scores.into_iter().max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PlayerRegion {
+    code: String,
+    // season of the last change, and the number of changes during it
+    season: u64,
+    changes: u8,
+    // best score counted in the region stats
+    best_score: i32,
+}
+
+/// Characters ranked by score, stored under the prefix given to `new`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RankedIndex {
+    // (score, account id, character) of the ranked characters
+    ranks: TreeMap<(i32, AccountId, String), ()>,
+    // ranked characters of each account with their score
+    accounts: LookupMap<AccountId, Vec<(String, i32)>>,
+}
+
+impl RankedIndex {
+    fn new(prefix: Vec<u8>) -> Self {
+        Self {
+            ranks: TreeMap::new([prefix.as_slice(), b"r"].concat()),
+            accounts: LookupMap::new([prefix.as_slice(), b"a"].concat()),
+        }
+    }
+
+    fn set(&mut self, account_id: &AccountId, character: &str, score: i32) {
+        let mut characters = self.accounts.get(account_id).unwrap_or_default();
+        if let Some(index) = characters.iter().position(|(name, _)| name == character) {
+            let (_, old_score) = characters.swap_remove(index);
+            self.ranks.remove(&(old_score, account_id.to_string(), character.to_string()));
+        }
+        characters.push((character.to_string(), score));
+        self.ranks.insert(&(score, account_id.to_string(), character.to_string()), &());
+        self.accounts.insert(account_id, &characters);
+    }
+
+    fn get(&self, account_id: &AccountId, character: &str) -> Option<i32> {
+        self.accounts.get(account_id)?.into_iter().find(|(name, _)| name == character).map(|(_, score)| score)
+    }
+
+    fn remove(&mut self, account_id: &AccountId, character: &str) {
+        let mut characters = self.accounts.get(account_id).unwrap_or_default();
+        if let Some(index) = characters.iter().position(|(name, _)| name == character) {
+            let (_, score) = characters.swap_remove(index);
+            self.ranks.remove(&(score, account_id.to_string(), character.to_string()));
+            if characters.is_empty() {
+                self.accounts.remove(account_id);
+            } else {
+                self.accounts.insert(account_id, &characters);
+            }
+        }
+    }
+
+    fn remove_account(&mut self, account_id: &AccountId) {
+        for (character, score) in self.accounts.remove(account_id).unwrap_or_default() {
+            self.ranks.remove(&(score, account_id.to_string(), character));
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.ranks.len()
+    }
+
+    /// Number of characters with at least the score, counted up to `max`
+    fn count_from(&self, score: i32, max: u64) -> u64 {
+        self.iter().take_while(|(other, _, _)| *other >= score).take(max as usize).count() as u64
+    }
+
+    /// (score, account id, character) from the best score
+    fn iter(&self) -> impl Iterator<Item = (i32, AccountId, String)> + '_ {
+        self.ranks.iter_rev().map(|(key, _)| key)
+    }
+}
+
+/// Entry of `get_region_leaderboard`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RegionEntry {
+    region: String,
+    players: u64,
+    total_score: i64,
+    average_score: i64,
+}
+
+impl RegionEntry {
+    fn new(region: &str) -> Self {
+        Self {
+            region: region.to_string(),
+            players: 0,
+            total_score: 0,
+            average_score: 0,
+        }
+    }
+
+    fn averaged(mut self) -> Self {
+        self.average_score = if self.players > 0 { self.total_score / self.players as i64 } else { 0 };
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RegionRanking {
+    Total,
+    Average,
+}
+
+/// Deadlines of the current round of a commit-reveal board, block timestamps in nanoseconds
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CommitRevealRules {
+    commit_end: U64,
+    reveal_end: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ScoreCommit {
+    hash: String,
+    board_id: String,
+    // round of the commit
+    commit_end: U64,
+    reveal_end: U64,
+}
+
+/// Score signed by a game server, see `submit_attested_score`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScoreAttestation {
+    account_id: AccountId,
+    board_id: String,
+    score: i32,
+    // unique per account
+    nonce: U64,
+    // block timestamp in nanoseconds
+    expiry: U64,
+}
+
+/// Replay of a play, by content hash and optionally where to fetch it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayProof {
+    // hex encoded SHA-256 of the replay
+    hash: String,
+    // IPFS content identifier
+    cid: Option<String>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ScoreRecord {
+    score: i32,
+    board_id: String,
+    timestamp: u64,
+    replay: Option<ReplayProof>,
+}
+
+/// Limits of the scores submitted to a board, see `set_board_rules`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardRules {
+    // bounds of the score, after the item multiplier
+    min_score: Option<i32>,
+    max_score: Option<i32>,
+    // above the best score of the character on the board
+    max_improvement: Option<i32>,
+    // between two submissions of an account, in nanoseconds
+    min_interval: Option<u64>,
+    // submissions of an account per UTC day
+    daily_cap: Option<u32>,
+}
+
+/// Error code of a submission breaking a rule of its board
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum ScoreRejection {
+    BelowMinimum,
+    AboveMaximum,
+    ImprovementTooLarge,
+    TooFrequent,
+    DailyCapReached,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct RejectionCounts {
+    below_minimum: u64,
+    above_maximum: u64,
+    improvement_too_large: u64,
+    too_frequent: u64,
+    daily_cap_reached: u64,
+}
+
+impl RejectionCounts {
+    fn add(&mut self, rejection: ScoreRejection) {
+        match rejection {
+            ScoreRejection::BelowMinimum => self.below_minimum += 1,
+            ScoreRejection::AboveMaximum => self.above_maximum += 1,
+            ScoreRejection::ImprovementTooLarge => self.improvement_too_large += 1,
+            ScoreRejection::TooFrequent => self.too_frequent += 1,
+            ScoreRejection::DailyCapReached => self.daily_cap_reached += 1,
+        }
+    }
+}
+
+/// Running stats of the scores of a board
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct BoardDistribution {
+    count: u64,
+    mean: f64,
+    // sum of the squared differences from the mean, see Welford's algorithm
+    m2: f64,
+    // bucket 0 counts the scores up to 0, bucket `i` the scores from 2^(i-1) to 2^i - 1
+    histogram: Vec<u64>,
+}
+
+impl Default for BoardDistribution {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            histogram: vec![0; HISTOGRAM_BUCKETS],
+        }
+    }
+}
+
+impl BoardDistribution {
+    fn add(&mut self, score: i32) {
+        self.count += 1;
+        let delta = score as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (score as f64 - self.mean);
+        let bucket = if score <= 0 { 0 } else { 32 - score.leading_zeros() as usize };
+        self.histogram[bucket] += 1;
+    }
+
+    fn variance(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.m2 / self.count as f64 }
+    }
+}
+
+impl Serialize for BoardDistribution {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BoardDistribution", 4)?;
+        state.serialize_field("count", &self.count)?;
+        state.serialize_field("mean", &self.mean)?;
+        state.serialize_field("variance", &self.variance())?;
+        state.serialize_field("histogram", &self.histogram)?;
+        state.end()
+    }
+}
+
+/// When a score is held for review, see `set_outlier_rules`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutlierRules {
+    // standard deviations above the mean of the board
+    max_deviations: f64,
+    // scores of the board needed before checking the deviations
+    min_samples: u64,
+    // a character with fewer submissions on the board is new, and reviewed when it enters the top 0.1%
+    new_account_submissions: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum OutlierReason {
+    Deviation,
+    NewAccountTop,
+}
+
+/// Suspicious score kept out of the ranking until a moderator approves it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct PendingReview {
+    account_id: AccountId,
+    character: String,
+    board_id: String,
+    score: i32,
+    replay: Option<ReplayProof>,
+    submitted_at: u64,
+    reason: OutlierReason,
+}
+
+/// Accepted submissions of an account on a board with rules
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct SubmissionWindow {
+    last_at: u64,
+    submissions: u64,
+    // UTC day of `day_submissions`, in days since the epoch
+    day: u64,
+    day_submissions: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChallengeRules {
+    // number of ranked players a new best score has to enter to be provisional
+    top: u32,
+    // in nanoseconds
+    window: u64,
+    bond: U128,
+}
+
+/// New top score waiting for its challenge window to end, see `finalize_score`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ProvisionalScore {
+    account_id: AccountId,
+    character: String,
+    board_id: String,
+    score: i32,
+    replay: Option<ReplayProof>,
+    submitted_at: u64,
+    dispute: Option<ScoreDispute>,
+}
+
+impl ProvisionalScore {
+    fn record(&self) -> ScoreRecord {
+        ScoreRecord {
+            score: self.score,
+            board_id: self.board_id.to_string(),
+            timestamp: self.submitted_at,
+            replay: self.replay.clone(),
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ScoreDispute {
+    challenger_id: AccountId,
+    bond: U128,
+    reason: String,
+}
+
+/// Recent submissions of a character as a ring buffer, with its stats per board
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ProfileHistory {
+    records: Vec<ScoreRecord>,
+    // index of the oldest record once the buffer is full
+    next: u32,
+    boards: HashMap<String, BoardStats>,
+}
+
+/// Stats of a character on a board, kept when the records are dropped from the history
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct BoardStats {
+    first: ScoreRecord,
+    best: ScoreRecord,
+    latest: ScoreRecord,
+    submissions: u64,
+}
+
+/// Returned by `get_score_improvement`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ScoreImprovement {
+    first: ScoreRecord,
+    best: ScoreRecord,
+    latest: ScoreRecord,
+    submissions: u64,
+    // best score minus the first one
+    improvement: i64,
+}
+
+impl ProfileHistory {
+    fn push(&mut self, record: ScoreRecord, depth: u32) {
+        match self.boards.get_mut(&record.board_id) {
+            Some(stats) => {
+                if stats.best.score < record.score {
+                    stats.best = record.clone();
+                }
+                stats.latest = record.clone();
+                stats.submissions += 1;
+            }
+            None => {
+                self.boards.insert(record.board_id.to_string(), BoardStats {
+                    first: record.clone(),
+                    best: record.clone(),
+                    latest: record.clone(),
+                    submissions: 1,
+                });
+            }
+        }
+
+        if self.records.len() > depth as usize {
+            // the depth was lowered, keep the newest records
+            let mut records = self.recent();
+            records.truncate(depth as usize - 1);
+            records.reverse();
+            self.records = records;
+            self.next = 0;
+        }
+        if self.records.len() < depth as usize {
+            self.records.push(record);
+        } else {
+            self.records[self.next as usize] = record;
+            self.next = (self.next + 1) % depth;
+        }
+    }
+
+    /// Records from the newest to the oldest
+    fn recent(&self) -> Vec<ScoreRecord> {
+        let (newer, older) = self.records.split_at(self.next as usize);
+        newer.iter().rev().chain(older.iter().rev()).cloned().collect()
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+pub struct GameSession {
+    // account which started the session, it may be linked to the profile playing
+    player_id: AccountId,
+    board_id: String,
+    started_at: u64,
+    submitted: bool,
+    deposit: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct SessionStats {
+    started: u64,
+    submitted: u64,
+    // ended or expired without a score
+    abandoned: u64,
+    open: u64,
+}
+
+/// Who can see a part of a profile
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    Public,
+    FriendsOnly,
+    Hidden,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PrivacySettings {
+    // first name, last name, age and attributes
+    info: Visibility,
+    // presence in the public leaderboard views
+    rank: Visibility,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            info: Visibility::Public,
+            rank: Visibility::Public,
+        }
+    }
+}
+
+/// Entry of `get_top_players`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LeaderBoardEntry {
+    account_id: AccountId,
+    character: String,
+    display_name: Option<String>,
+    avatar: Option<Avatar>,
+    score: i32,
+}
+
+/// NFT shown as the avatar of a player
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Avatar {
+    contract_id: AccountId,
+    token_id: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DisplayName {
+    name: String,
+    // key of the name in `name_owners`
+    key: String,
+    changed_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Season {
+    standings: Vector<(String, i32)>,
+    winners: Vec<SeasonWinner>,
+    prize_pool: Balance,
+    finalized_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+pub struct SeasonWinner {
+    account_id: AccountId,
+    rank: u64,
+    score: i32,
+    trophy: String,
+    prize: U128,
+    prize_status: PayoutStatus,
+    trophy_status: PayoutStatus,
+}
+
+#[derive(Serialize)]
+pub struct TrophyMetadata {
+    title: String,
+    description: String,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EquippedItem {
+    contract_id: AccountId,
+    token_id: u64,
+    // multiplier read from the item metadata, the bounds are applied when a score is saved
+    multiplier: u32,
+}
+
+/// Token metadata of the item contract, only `extra` is used
+#[derive(Deserialize)]
+pub struct ItemMetadata {
+    extra: Option<String>,
+}
+
+/// JSON stored in the `extra` metadata field of items
+#[derive(Deserialize)]
+struct ItemAttributes {
+    score_multiplier: Option<u32>,
+}
+
+impl ItemMetadata {
+    fn score_multiplier(&self) -> Option<u32> {
+        let extra = self.extra.as_ref()?;
+        serde_json::from_str::<ItemAttributes>(extra).ok()?.score_multiplier
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum PayoutStatus {
+    Unpaid,
+    Sending,
+    Paid,
+}
+
+// --- START: Utility --- //
+/// Logs a NEP-297 event of the leaderboard
+fn log_event(event: &str, data: serde_json::Value) {
+    let event = json!({
+        "standard": EVENT_STANDARD,
+        "version": EVENT_VERSION,
+        "event": event,
+        "data": data,
+    });
+    env::log(format!("EVENT_JSON:{}", event).as_bytes());
+}
+
+/// Removes the account from the list of another one, returns `false` if it was not in it
+fn remove_from_list(map: &mut UnorderedMap<AccountIdHash, Vec<AccountId>>, key: &AccountIdHash, account_id: &str) -> bool {
+    let mut list = map.get(key).unwrap_or_default();
+    let len = list.len();
+    list.retain(|item| item != account_id);
+    if list.is_empty() {
+        map.remove(key);
+    } else {
+        map.insert(key, &list);
+    }
+    list.len() < len
+}
+
+/// Storage prefix of the characters of an account, `:` can not be in an account id nor in the prefixes of the state
+fn profiles_prefix(account_hash: &AccountIdHash) -> Vec<u8> {
+    [b"p:".as_slice(), account_hash].concat()
+}
+
+/// Storage prefix of the ranking of a board, hashed as board ids are not a fixed length
+fn board_ranks_prefix(board_id: &str) -> Vec<u8> {
+    [b"board:".as_slice(), &env::sha256(board_id.as_bytes())].concat()
+}
+
+/// Key of the history of a character in `score_histories`
+fn history_key(account_hash: &AccountIdHash, character: &str) -> Vec<u8> {
+    [account_hash.as_slice(), character.as_bytes()].concat()
+}
+
+fn commit_key(account_id: &str, character: &str, board_id: &str) -> Vec<u8> {
+    env::sha256(&(account_id, character, board_id).try_to_vec().unwrap())
+}
+
+fn board_key(account_hash: &AccountIdHash, board_id: &str) -> Vec<u8> {
+    [account_hash.as_slice(), b":", board_id.as_bytes()].concat()
+}
+
+/// Moves the value of an account to another one
+fn move_value<V: BorshSerialize + BorshDeserialize>(map: &mut UnorderedMap<Vec<u8>, V>, from: &Vec<u8>, to: &Vec<u8>) {
+    if let Some(value) = map.remove(from) {
+        map.insert(to, &value);
+    }
+}
+
+/// NFC form with the whitespaces trimmed and collapsed
+fn collapse_name(name: &str) -> String {
+    name.nfc().collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Names stored before the validation are kept, as far as they can be
+fn truncate_name(name: &str) -> String {
+    name.chars().filter(|c| !c.is_control()).take(MAX_NAME_LENGTH).collect()
+}
+
+/// Collapsed name, panics if it is too long or contains control characters
+fn normalize_name(name: &str) -> String {
+    let normalized = collapse_name(name);
+    assert!(!normalized.chars().any(char::is_control), "Names can not contain control characters.");
+    assert!(normalized.chars().count() <= MAX_NAME_LENGTH, "Names can not be longer than {} characters.", MAX_NAME_LENGTH);
+    normalized
+}
+
+/// Collapsed display name, panics if it is too short, too long or has forbidden characters
+fn normalize_display_name(name: &str) -> String {
+    let normalized = collapse_name(name);
+    let length = normalized.chars().count();
+    assert!(
+        DISPLAY_NAME_LENGTH.0 <= length && length <= DISPLAY_NAME_LENGTH.1,
+        "Display names should have between {} and {} characters.", DISPLAY_NAME_LENGTH.0, DISPLAY_NAME_LENGTH.1
+    );
+    assert!(
+        normalized.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '_' || c == '-'),
+        "Display names can only contain letters, digits, spaces, '_' and '-'."
+    );
+    normalized
+}
+
+/// Same key for the names differing only by case or by look-alike characters (UTS #39 skeleton)
+fn display_name_key(name: &str) -> String {
+    // lowercase before and after, some skeletons are uppercase letters ("0" is "O")
+    let skeleton = unicode_security::skeleton(&name.to_lowercase()).collect::<String>().to_lowercase();
+    unicode_security::skeleton(&skeleton).collect()
+}
+
+fn get_sorted_profiles_by_score(list: &UnorderedMap<String, Profile>, only_highest: bool) -> Vec<(String, Profile)> {
+    let mut result = list.to_vec();
+    return mut_to_sorted_vec(&mut result, only_highest);
+}
+
+/// Characters ordered by score like `mut_to_sorted_vec`, keeping their character names
+fn sort_characters(mut list: Vec<(String, Profile)>) -> Vec<(String, Profile)> {
+    list.sort_by_key(|(_, profile)| std::cmp::Reverse(profile.score));
+    list
+}
+
+fn mut_to_sorted_vec(list: &mut Vec<(String, Profile)>, only_highest: bool) -> Vec<(String, Profile)> {
+    let mut vec: Vec<(String, Profile)> = vec![];
+    while list.len() > 0 {
+        let mut profile: Profile = Profile::new(String::new());
+        let mut max_at_index = 0;
+        for index in 0..list.len() {
+            let value: &Profile = &list[index].1;
+            // compare the fields directly, `get_score` logs and would exceed the logs limit
+            if profile.score == 0 || profile.score < value.score {
+                profile = Profile{
+                    username: value.get_username(),
+                    score: value.score,
+                    hourly_reward_at: value.hourly_reward_at,
+                };
+                max_at_index = index;
+            }
+        }
+
+        list.remove(max_at_index);
+        vec.push((profile.get_username(), profile));
+        if only_highest {
+            break;
+        }
+    }
+
+    return vec;
+}
+// --- END: Utility --- //
+
+#[cfg(test)]
+// the baseline tests keep their original style
+#[allow(clippy::bool_assert_comparison, clippy::needless_return, clippy::useless_asref)]
+mod tests {
+    use super::*;
+    use near_sdk::MockedBlockchain;
+    use near_sdk::test_utils::get_logs;
+    use near_sdk::{testing_env, VMContext};
+
+    fn get_context(input: Vec<u8>, is_view: bool) -> VMContext {
+        VMContext {
+            current_account_id: "alice.testnet".to_string(),
+            signer_account_id: "robert.testnet".to_string(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id: "jane.testnet".to_string(),
+            input,
+            block_index: 0,
+            block_timestamp: 0,
+            account_balance: 0,
+            account_locked_balance: 0,
+            storage_usage: 0,
+            attached_deposit: 0,
+            prepaid_gas: 10u64.pow(18),
+            random_seed: vec![0, 1, 2],
+            is_view,
+            output_data_receivers: vec![],
+            epoch_height: 19,
+        }
+    }
+
+    fn get_test_map() -> UnorderedMap<String, Profile> {
+        let mut map:UnorderedMap<String, Profile> = UnorderedMap::new(b"test".to_vec());
+        for i in [1,2,3,9] {
+            map.insert(&i.to_string(), &Profile{
+                hourly_reward_at: 0,
+                score: i,
+                username: i.to_string(),
+            });
+        };
+
+        return map;
+    }
+
+    #[test]
+    fn test_all() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let mut contract = ProfileState::new();
+        let account1: &str = "viigstar-2.testnet";
+        contract.grant_access(account1.to_string());
+        contract.grant_access(account1.to_string());
+        contract.revoke_access("viigstar-nft.testnet".to_string());
+        contract.revoke_access(account1.to_string());
+    }
+
+    #[test]
+    fn test_grant_access() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let mut contract = ProfileState::new();
+        let account1: &str = "viigstar-2.testnet";
+        let escrow_hash = env::sha256(account1.as_bytes());
+        contract.grant_access(account1.to_string());
+        assert_eq!(true, contract.check_access(account1.to_string()));
+        assert_eq!(1, contract.state.get(&escrow_hash).unwrap().len());
+        for key in contract.state.get(&escrow_hash).unwrap().keys() {
+            assert_eq!(account1.to_string(), key);
+        }
+        for mut user in contract.get_list_user(account1.to_string()) {
+            assert_eq!(account1.to_string(), user.username);
+            let reward_at = Utc::now().timestamp_millis();
+            user.rewarded(reward_at);
+            assert_eq!(reward_at, user.hourly_reward_at);
+            println!("rewarded at {}", reward_at);
+        }
+    }
+
+    #[test]
+    fn test_revoke_access() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let mut contract = ProfileState::new();
+        let account1: &str = "viigstar-2.testnet";
+        contract.grant_access(account1.to_string());
+        assert_eq!(true, contract.check_access(account1.to_string()));
+        contract.revoke_access(account1.to_string());
+        assert_eq!(false, contract.check_access(account1.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the account or the contract owner can revoke its access.")]
+    fn test_revoke_access_of_other_account() {
+        let mut contract = contract_with_players(&["player.testnet", "mallory.testnet"]);
+        player_context("mallory.testnet");
+        contract.revoke_access("player.testnet".to_string());
+    }
+
+    #[test]
+    fn test_flat_users() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let map = get_test_map();
+        let mut contract = ProfileState::new();
+        let escrow_hash = env::sha256("1".as_bytes());
+        contract.state.insert(&escrow_hash, &map);
+        assert_eq!(4, contract.flat_users().len());
+        assert_eq!("1".to_string(), contract.flat_users()[0].0);
+        assert_eq!("9".to_string(), contract.flat_users()[3].0);
+    }
+
+    #[test]
+    fn test_get_username() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let mut contract = ProfileState::new();
+        let account1: &str = "viigstar-2.testnet";
+        let escrow_hash = env::sha256(account1.as_bytes());
+        contract.grant_access(account1.to_string());
+        for profile in contract.state.get(&escrow_hash).unwrap().values() {
+            assert_eq!(account1.to_string(), profile.get_username());
+        }
+    }
+
+    #[test]
+    fn test_get_score() {
+        // set up the mock context into the testing environment
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let contract = Profile::new(String::new());
+        assert_eq!(0, contract.get_score());
+        println!("test get_score succeeded");
+    }
+
+    #[test]
+    fn test_set_score() {
+        // set up the mock context into the testing environment
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let mut contract = Profile::new(String::new());
+        contract.set_score(28);
+        assert_eq!(28, contract.get_score());
+        println!("test set_score succeeded");
+    }
+
+    #[test]
+    fn test_save_new_score() {
+        // set up the mock context into the testing environment
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let account_ref = "1";
+        let map = get_test_map();
+        let mut contract = ProfileState::new();
+        contract.state.insert(&env::sha256(account_ref.as_bytes()), &map);
+        // assert_eq!(10, contract.get_reward(account_ref.to_string()));
+        assert_eq!(true, contract.save_new_score(account_ref.to_string(), None, 22));
+        assert_eq!(50, contract.get_reward(account_ref.to_string()));
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let contract = Profile::new(String::new());
+        assert_eq!(true, contract.is_empty());
+        println!("test is_empty succeeded");
+    }
+
+    #[test]
+    fn test_get_sorted_profiles_by_score() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let map = get_test_map();
+        println!("length before mutate reference {}", map.len());
+        let result = get_sorted_profiles_by_score(&map, false);
+        println!("length after mutate reference {}", map.len());
+        assert_eq!(9, result[0].1.score);
+        assert_eq!(3, result[1].1.score);
+        assert_eq!(2, result[2].1.score);
+        assert_eq!(1, result[3].1.score);
+    }
+
+    #[test]
+    fn test_get_top_players() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let map = get_test_map();
+        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
+        for i in [4,5,7,11] {
+            map2.insert(&i.to_string(), &Profile{
+                hourly_reward_at: 0,
+                score: i,
+                username: i.to_string(),
+            });
+        };
+
+        let mut contract = ProfileState::new();
+        contract.state.insert(&env::sha256("1".as_bytes()), &map);
+        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
+        let mut flat_users = contract.flat_users();
+        assert_eq!(8, flat_users.len());
+        let sorted_flat = mut_to_sorted_vec(&mut flat_users, false);
+        assert_eq!(8, sorted_flat.len());
+        assert_eq!(11, sorted_flat[0].1.score);
+        assert_eq!(9, sorted_flat[1].1.score);
+        assert_eq!(1, sorted_flat[7].1.score);
+    }
+
+    #[test]
+    fn test_get_reward() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let map = get_test_map();
+        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
+        for i in [4,5,7,45] {
+            map2.insert(&i.to_string(), &Profile{
+                hourly_reward_at: 0,
+                score: i,
+                username: i.to_string(),
+            });
+        };
+
+        let mut contract = ProfileState::new();
+        let first_player:&str = "1".as_ref();
+        let second_player = String::from("4");
+        contract.state.insert(&env::sha256(first_player.to_string().as_bytes()), &map);
+        assert_eq!(10, contract.get_reward(first_player.to_string()));
+
+        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
+        assert_eq!(50, contract.get_reward(second_player.to_string()));
+        assert_eq!(0, contract.get_reward(second_player.to_string()));
+    }
+
+    #[test]
+    fn test_check_time_reward() {
+        let context = get_context(vec![], false);
+        testing_env!(context);
+        let map = get_test_map();
+        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
+        for i in [4,5,7,45] {
+            map2.insert(&i.to_string(), &Profile{
+                hourly_reward_at: 0,
+                score: i,
+                username: i.to_string(),
+            });
+        };
+
+        let mut contract = ProfileState::new();
+        let first_player:&str = "1".as_ref();
+        let second_player = String::from("4");
+        contract.state.insert(&env::sha256(first_player.to_string().as_bytes()), &map);
+        // not rewarded yet
+        assert_eq!(false, contract.is_recently_rewarded(first_player.to_string()));
+        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
+        assert_eq!(false, contract.is_recently_rewarded(first_player.to_string()));
+
+        // rewarded correct value in range
+        assert_eq!(10, contract.get_reward(first_player.to_string()));
+        assert_eq!(50, contract.get_reward(second_player.to_string()));
+
+        // rewarded check
+        assert_eq!(true, contract.is_recently_rewarded(first_player.to_string()));
+        assert_eq!(true, contract.is_recently_rewarded(second_player.to_string()));
+    }
+
+    fn finalized_contract(prize_pool: Balance) -> ProfileState {
+        let mut context = get_context(vec![], false);
+        context.attached_deposit = prize_pool;
+        context.account_balance = prize_pool;
+        context.prepaid_gas = 300_000_000_000_000;
+        testing_env!(context);
+        let mut contract = ProfileState::new();
+        let mut map: UnorderedMap<String, Profile> = UnorderedMap::new(b"season".to_vec());
+        for i in 1..=12 {
+            map.insert(&i.to_string(), &Profile{
+                hourly_reward_at: 0,
+                score: i * 10,
+                username: i.to_string(),
+            });
+        };
+        contract.state.insert(&env::sha256("season".as_bytes()), &map);
+        contract.set_trophy_contract("trophy.testnet".to_string());
+        assert_eq!(0, contract.finalize_season());
+        contract
+    }
+
+    #[test]
+    fn test_finalize_season() {
+        let contract = finalized_contract(1_000_000);
+
+        // later scores do not change the frozen standings
+        contract.state.get(&env::sha256("season".as_bytes())).unwrap().insert(&"1".to_string(), &Profile{
+            hourly_reward_at: 0,
+            score: 999,
+            username: "1".to_string(),
+        });
+        let standings = contract.get_season_standings(0, 0, 20);
+        assert_eq!(12, standings.len());
+        assert_eq!(("12".to_string(), 120), standings[0]);
+        assert_eq!(("1".to_string(), 10), standings[11]);
+        assert_eq!(vec![("10".to_string(), 100)], contract.get_season_standings(0, 2, 1));
+
+        let winners = contract.get_season_winners(0);
+        assert_eq!(10, winners.len());
+        assert_eq!("gold", winners[0].trophy);
+        assert_eq!(350_000, winners[0].prize.0);
+        assert_eq!("silver", winners[2].trophy);
+        assert_eq!(150_000, winners[2].prize.0);
+        assert_eq!("bronze", winners[9].trophy);
+        assert_eq!(50_000, winners[9].prize.0);
+        assert_eq!(1_000_000, winners.iter().map(|winner| winner.prize.0).sum::<u128>());
+    }
+
+    #[test]
+    fn test_finalize_season_with_few_winners() {
+        let mut contract = contract_with_players(&["player.testnet", "rival.testnet"]);
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 50));
+        assert!(contract.save_new_score("rival.testnet".to_string(), None, 40));
+        call_context("jane.testnet", 0, 1_000_000);
+        contract.finalize_season();
+
+        // gold and silver split the whole pool 35:15, nothing stays in the contract
+        let winners = contract.get_season_winners(0);
+        assert_eq!(2, winners.len());
+        assert_eq!(700_000, winners[0].prize.0);
+        assert_eq!(300_000, winners[1].prize.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "A season with a prize pool needs at least one ranked player.")]
+    fn test_finalize_season_prize_without_winners() {
+        let mut contract = contract_with_players(&[]);
+        call_context("jane.testnet", 0, 1_000_000);
+        contract.finalize_season();
+    }
+
+    #[test]
+    fn test_finalize_season_without_hidden_ranks() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        for (account_id, score) in [("player.testnet", 50), ("shy.testnet", 60)] {
+            contract.grant_access(account_id.to_string());
+            assert!(contract.save_new_score(account_id.to_string(), None, score));
+        }
+        player_context("shy.testnet");
+        contract.set_privacy(PrivacySettings { info: Visibility::Public, rank: Visibility::Hidden });
+        player_context("jane.testnet");
+        contract.finalize_season();
+        assert_eq!(vec![("player.testnet".to_string(), 50)], contract.get_season_standings(0, 0, 10));
+        let winners = contract.get_season_winners(0);
+        assert_eq!(("player.testnet".to_string(), 1), (winners[0].account_id.to_string(), winners[0].rank));
+
+        // hiding the rank later hides it from the finalized season too
+        player_context("player.testnet");
+        contract.set_privacy(PrivacySettings { info: Visibility::Public, rank: Visibility::Hidden });
+        assert!(contract.get_season_standings(0, 0, 10).is_empty());
+        assert!(contract.get_season_winners(0).is_empty());
+    }
+
+    #[test]
+    fn test_finalize_season_once_per_account() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.grant_access("rival.testnet".to_string());
+        player_context("player.testnet");
+        contract.create_character("Mage".to_string());
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("player.testnet".to_string(), Some("Mage".to_string()), 45));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 30));
+        assert!(contract.save_new_score("rival.testnet".to_string(), None, 20));
+        contract.finalize_season();
+        assert_eq!(vec![("player.testnet".to_string(), 45), ("rival.testnet".to_string(), 20)], contract.get_season_standings(0, 0, 10));
+        assert_eq!(vec![1, 2], contract.get_season_winners(0).into_iter().map(|winner| winner.rank).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only contract owner can call this method.")]
+    fn test_finalize_season_not_owner() {
+        let mut contract = finalized_contract(0);
+        let mut context = get_context(vec![], false);
+        context.predecessor_account_id = "mallory.testnet".to_string();
+        testing_env!(context);
+        contract.finalize_season();
+    }
+
+    // fresh call of the contract, keeping the storage of the previous calls
+    fn next_call(promise_results: Vec<PromiseResult>, prepaid_gas: Gas) {
+        let mut context = get_context(vec![], false);
+        context.account_balance = 1_000_000;
+        context.storage_usage = env::storage_usage();
+        context.prepaid_gas = prepaid_gas;
+        testing_env!(context, Default::default(), Default::default(), Default::default(), promise_results);
+    }
+
+    #[test]
+    fn test_distribute_season_prizes_in_batches() {
+        let mut contract = finalized_contract(1_000_000);
+        assert_eq!(3, contract.distribute_season_prizes(0, 3));
+        next_call(vec![], 300_000_000_000_000);
+        assert_eq!(MAX_SEASON_PAYOUT_BATCH, contract.distribute_season_prizes(0, 100));
+        // not enough gas left for a second winner
+        next_call(vec![], GAS_FOR_SEASON_PAYOUT + GAS_FOR_SEASON_PAYOUT / 2);
+        assert_eq!(1, contract.distribute_season_prizes(0, 100));
+        next_call(vec![], 300_000_000_000_000);
+        assert_eq!(2, contract.distribute_season_prizes(0, 100));
+        // every winner is already being paid, a retry does nothing
+        next_call(vec![], 300_000_000_000_000);
+        assert_eq!(0, contract.distribute_season_prizes(0, 100));
+        for winner in contract.get_season_winners(0) {
+            assert_eq!(PayoutStatus::Sending, winner.prize_status);
+            assert_eq!(PayoutStatus::Sending, winner.trophy_status);
+        }
+    }
+
+    #[test]
+    fn test_season_payout_callbacks() {
+        let mut contract = finalized_contract(1_000_000);
+        assert_eq!(2, contract.distribute_season_prizes(0, 2));
+
+        next_call(vec![PromiseResult::Successful(vec![])], 300_000_000_000_000);
+        assert!(contract.on_season_payout(0, 0, false));
+        assert!(contract.on_season_payout(0, 0, true));
+        next_call(vec![PromiseResult::Failed], 300_000_000_000_000);
+        assert!(!contract.on_season_payout(0, 1, false));
+
+        let winners = contract.get_season_winners(0);
+        assert_eq!(PayoutStatus::Paid, winners[0].prize_status);
+        assert_eq!(PayoutStatus::Paid, winners[0].trophy_status);
+        assert_eq!(PayoutStatus::Unpaid, winners[1].prize_status);
+        assert_eq!(PayoutStatus::Sending, winners[1].trophy_status);
+
+        // only the failed prize is sent again, the first winner is never paid twice
+        next_call(vec![], 300_000_000_000_000);
+        assert_eq!(MAX_SEASON_PAYOUT_BATCH, contract.distribute_season_prizes(0, 100));
+        let winners = contract.get_season_winners(0);
+        assert_eq!(PayoutStatus::Paid, winners[0].prize_status);
+        assert_eq!(PayoutStatus::Sending, winners[1].prize_status);
+        assert_eq!(PayoutStatus::Unpaid, winners[6].prize_status);
+    }
+
+    fn item_context(predecessor_account_id: &str, promise_results: Vec<PromiseResult>) {
+        let mut context = get_context(vec![], false);
+        context.predecessor_account_id = predecessor_account_id.to_string();
+        context.storage_usage = env::storage_usage();
+        testing_env!(context, Default::default(), Default::default(), Default::default(), promise_results);
+    }
+
+    fn equipped_contract(multiplier: u32) -> ProfileState {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.set_item_contract("items.testnet".to_string());
+        contract.set_multiplier_bounds(5_000, 15_000);
+        contract.grant_access("player.testnet".to_string());
+
+        item_context("items.testnet", vec![]);
+        match contract.nft_on_transfer("player.testnet".to_string(), "player.testnet".to_string(), 7, String::new()) {
+            PromiseOrValue::Promise(_) => {}
+            PromiseOrValue::Value(_) => panic!("expected the item metadata to be requested"),
+        }
+        let metadata = format!(r#"{{"title":"Sword","extra":"{{\"score_multiplier\":{}}}"}}"#, multiplier);
+        item_context("alice.testnet", vec![PromiseResult::Successful(metadata.into_bytes())]);
+        assert!(!contract.on_item_metadata("player.testnet".to_string(), 7));
+        player_context("jane.testnet");
+        contract
+    }
+
+    #[test]
+    fn test_equipped_item_multiplies_score() {
+        let mut contract = equipped_contract(12_500);
+        assert_eq!(Some(EquippedItem {
+            contract_id: "items.testnet".to_string(),
+            token_id: 7,
+            multiplier: 12_500,
+        }), contract.get_equipped_item("player.testnet".to_string()));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 100));
+        assert_eq!(125, contract.get_list_user("player.testnet".to_string())[0].score);
+    }
+
+    #[test]
+    fn test_equipped_item_multiplier_is_bounded() {
+        let mut contract = equipped_contract(40_000);
+        assert_eq!(15_000, contract.get_score_multiplier("player.testnet".to_string()));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, i32::MAX));
+        assert_eq!(i32::MAX, contract.get_list_user("player.testnet".to_string())[0].score);
+        assert_eq!(MULTIPLIER_DENOMINATOR, contract.get_score_multiplier("other.testnet".to_string()));
+    }
+
+    #[test]
+    fn test_item_without_multiplier_is_returned() {
+        let mut contract = equipped_contract(12_500);
+        item_context("items.testnet", vec![]);
+        // already equipped
+        match contract.nft_on_transfer("player.testnet".to_string(), "player.testnet".to_string(), 8, String::new()) {
+            PromiseOrValue::Value(should_return) => assert!(should_return),
+            PromiseOrValue::Promise(_) => panic!("expected the item to be returned"),
+        }
+        contract.grant_access("other.testnet".to_string());
+        item_context("alice.testnet", vec![PromiseResult::Successful(br#"{"title":"Shield"}"#.to_vec())]);
+        assert!(contract.on_item_metadata("other.testnet".to_string(), 9));
+        assert_eq!(None, contract.get_equipped_item("other.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the item contract can equip items.")]
+    fn test_equip_from_unknown_contract() {
+        let mut contract = equipped_contract(12_500);
+        item_context("fake-items.testnet", vec![]);
+        contract.nft_on_transfer("player.testnet".to_string(), "player.testnet".to_string(), 8, String::new());
+    }
+
+    #[test]
+    fn test_unequip_item() {
+        let mut contract = equipped_contract(12_500);
+        item_context("player.testnet", vec![]);
+        contract.unequip_item();
+        assert_eq!(None, contract.get_equipped_item("player.testnet".to_string()));
+
+        // the transfer failed, the item is still in custody
+        item_context("alice.testnet", vec![PromiseResult::Failed]);
+        assert!(!contract.on_item_unequipped("player.testnet".to_string(), EquippedItem {
+            contract_id: "items.testnet".to_string(),
+            token_id: 7,
+            multiplier: 12_500,
+        }));
+        assert_eq!(12_500, contract.get_score_multiplier("player.testnet".to_string()));
+
+        // another item was equipped before the failed transfer was known
+        item_context("alice.testnet", vec![PromiseResult::Failed]);
+        assert!(!contract.on_item_unequipped("player.testnet".to_string(), EquippedItem {
+            contract_id: "items.testnet".to_string(),
+            token_id: 6,
+            multiplier: 11_000,
+        }));
+        assert_eq!(7, contract.get_equipped_item("player.testnet".to_string()).unwrap().token_id);
+    }
+
+    fn avatar_contract() -> ProfileState {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.add_avatar_contract("nft.testnet".to_string());
+        contract.grant_access("player.testnet".to_string());
+        item_context("player.testnet", vec![]);
+        contract.set_avatar("nft.testnet".to_string(), 3);
+        item_context("alice.testnet", vec![PromiseResult::Successful(br#""player.testnet""#.to_vec())]);
+        assert!(contract.on_avatar_owner("player.testnet".to_string(), Avatar { contract_id: "nft.testnet".to_string(), token_id: 3 }, true));
+        contract
+    }
+
+    #[test]
+    fn test_set_avatar() {
+        let mut contract = avatar_contract();
+        let avatar = Avatar { contract_id: "nft.testnet".to_string(), token_id: 3 };
+        assert_eq!(Some(avatar.clone()), contract.get_avatar("player.testnet".to_string()));
+        assert_eq!(Some(avatar.clone()), contract.get_top_players()[0].avatar);
+
+        // still owned
+        item_context("anyone.testnet", vec![]);
+        contract.refresh_avatar("player.testnet".to_string());
+        item_context("alice.testnet", vec![PromiseResult::Successful(br#""player.testnet""#.to_vec())]);
+        assert!(contract.on_avatar_owner("player.testnet".to_string(), avatar.clone(), false));
+        assert_eq!(Some(avatar.clone()), contract.get_avatar("player.testnet".to_string()));
+
+        // transferred away
+        item_context("alice.testnet", vec![PromiseResult::Successful(br#""buyer.testnet""#.to_vec())]);
+        assert!(!contract.on_avatar_owner("player.testnet".to_string(), avatar, false));
+        assert_eq!(None, contract.get_avatar("player.testnet".to_string()));
+    }
+
+    #[test]
+    fn test_set_avatar_not_owned() {
+        let mut contract = avatar_contract();
+        // burned tokens have no owner
+        item_context("alice.testnet", vec![PromiseResult::Failed]);
+        assert!(!contract.on_avatar_owner("player.testnet".to_string(), Avatar { contract_id: "nft.testnet".to_string(), token_id: 4 }, true));
+        // the current avatar is not the one checked
+        assert!(contract.get_avatar("player.testnet".to_string()).is_some());
+
+        item_context("player.testnet", vec![]);
+        contract.clear_avatar();
+        assert_eq!(None, contract.get_avatar("player.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "NFT contract is not allowed for avatars.")]
+    fn test_set_avatar_from_unknown_contract() {
+        let mut contract = avatar_contract();
+        item_context("player.testnet", vec![]);
+        contract.set_avatar("fake-nft.testnet".to_string(), 3);
+    }
+
+    fn player_context(predecessor_account_id: &str) {
+        call_context(predecessor_account_id, 0, 0);
+    }
+
+    // the contract keeps its storage and balance from one call to the next, like on chain
+    fn call_context(predecessor_account_id: &str, block_timestamp: u64, attached_deposit: Balance) {
+        let mut context = get_context(vec![], false);
+        context.predecessor_account_id = predecessor_account_id.to_string();
+        context.storage_usage = env::storage_usage();
+        context.account_balance = env::account_balance() + attached_deposit;
+        context.block_timestamp = block_timestamp;
+        context.attached_deposit = attached_deposit;
+        testing_env!(context);
+    }
+
+    // a new contract owned by the caller of `get_context`, with a profile for each player
+    fn contract_with_players(players: &[&str]) -> ProfileState {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        for account_id in players {
+            contract.grant_access(account_id.to_string());
+        }
+        contract
+    }
+
+    #[test]
+    fn test_set_info() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        let account1: &str = "viigstar-2.testnet";
+        assert_eq!(None, contract.get_info(account1.to_string()));
+        contract.grant_access(account1.to_string());
+        assert!(contract.save_new_score(account1.to_string(), None, 30));
+        player_context(account1);
+        assert!(contract.set_info(account1.to_string(), Some(28), Some("Trung".to_string()), Some(String::from("Nguyen  Bao "))));
+        assert_eq!("Nguyen Bao", contract.get_info(account1.to_string()).unwrap().last_name);
+        // missing values leave the info unchanged
+        assert!(contract.set_info(account1.to_string(), None, None, Some("Nguyen".to_string())));
+        assert_eq!(Some(ProfileInfo {
+            account_id: account1.to_string(),
+            first_name: "Trung".to_string(),
+            last_name: "Nguyen".to_string(),
+            age: Some(28),
+            attributes: HashMap::new(),
+            score: 30,
+            hourly_reward_at: 0,
+        }), contract.get_info(account1.to_string()));
+        assert_eq!(
+            "(\"My name is Trung\", \"Full name is Nguyen Trung\", \"I'm 28 years old\")".to_string(),
+            format!("{:?}", contract.introduce(account1.to_string()).unwrap())
+        );
+
+        assert!(contract.clear_info(account1.to_string(), vec![InfoField::Age, InfoField::LastName]));
+        let info = contract.get_info(account1.to_string()).unwrap();
+        assert_eq!(("Trung".to_string(), String::new(), None), (info.first_name, info.last_name, info.age));
+
+        contract.revoke_access(account1.to_string());
+        assert_eq!(None, contract.get_info(account1.to_string()));
+    }
+
+    #[test]
+    fn test_set_info_normalizes_names() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        // "e" followed by a combining acute accent is stored as a single "é"
+        assert!(contract.set_info("jane.testnet".to_string(), None, Some("Ame\u{301}lie".to_string()), None));
+        assert_eq!("Am\u{e9}lie", contract.get_info("jane.testnet".to_string()).unwrap().first_name);
+        // no profile to set the info of
+        player_context("unknown.testnet");
+        assert!(!contract.set_info("unknown.testnet".to_string(), None, Some("Unknown".to_string()), None));
+    }
+
+    #[test]
+    #[should_panic(expected = "Names can not be longer than 64 characters.")]
+    fn test_set_info_name_too_long() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        contract.set_info("jane.testnet".to_string(), None, Some("é".repeat(65)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Age should be between 13 and 120.")]
+    fn test_set_info_age_out_of_range() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        contract.set_info("jane.testnet".to_string(), Some(121), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the profile owner or its editors can edit its info.")]
+    fn test_set_info_of_another_account() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("viigstar-2.testnet".to_string());
+        contract.set_info("viigstar-2.testnet".to_string(), Some(28), Some("Trung".to_string()), None);
+    }
+
+    #[test]
+    fn test_profile_editors() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.add_profile_editor("support.testnet".to_string());
+        contract.add_profile_editor("support.testnet".to_string());
+        assert_eq!(vec!["support.testnet".to_string()], contract.get_profile_editors("player.testnet".to_string()));
+
+        player_context("support.testnet");
+        assert!(contract.set_info("player.testnet".to_string(), Some(40), None, None));
+        assert!(contract.clear_info("player.testnet".to_string(), vec![InfoField::Age]));
+        assert_eq!(None, contract.get_info("player.testnet".to_string()).unwrap().age);
+
+        player_context("player.testnet");
+        contract.remove_profile_editor("support.testnet".to_string());
+        assert!(contract.get_profile_editors("player.testnet".to_string()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the profile owner or its editors can edit its info.")]
+    fn test_removed_editor_can_not_edit() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.add_profile_editor("support.testnet".to_string());
+        contract.remove_profile_editor("support.testnet".to_string());
+        player_context("support.testnet");
+        contract.clear_info("player.testnet".to_string(), vec![InfoField::FirstName]);
+    }
+
+    #[test]
+    fn test_characters() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.create_character("Mage".to_string());
+        contract.create_character("Rogue".to_string());
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("player.testnet".to_string(), Some("Mage".to_string()), 45));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 5));
+        assert!(!contract.save_new_score("player.testnet".to_string(), Some("Knight".to_string()), 5));
+        player_context("player.testnet");
+
+        contract.rename_character("Mage".to_string(), "Archmage".to_string());
+        contract.delete_character("Rogue".to_string());
+        let mut characters = contract.get_characters("player.testnet".to_string());
+        characters.sort_by_key(|(name, _)| name.to_string());
+        assert_eq!(vec![("Archmage".to_string(), 45), ("player.testnet".to_string(), 5)],
+                   characters.into_iter().map(|(name, profile)| (name, profile.score)).collect::<Vec<(String, i32)>>());
+        let top_players = contract.get_top_players();
+        assert_eq!(("player.testnet".to_string(), "Archmage".to_string()), (top_players[0].account_id.to_string(), top_players[0].character.to_string()));
+        assert_eq!(Some(1), contract.get_own_rank());
+
+        // paid once, based on the best character
+        assert_eq!(50, contract.get_reward("player.testnet".to_string()));
+        assert_eq!(0, contract.get_reward("player.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "A profile can not have more than 5 characters.")]
+    fn test_too_many_characters() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        for name in ["one", "two", "three", "four", "five"] {
+            contract.create_character(name.to_string());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The default character can not be renamed.")]
+    fn test_rename_default_character() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.rename_character("player.testnet".to_string(), "Knight".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The default character can not be deleted.")]
+    fn test_delete_default_character() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.delete_character("player.testnet".to_string());
+    }
+
+    #[test]
+    fn test_profile_transfer() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("old.testnet".to_string());
+        player_context("old.testnet");
+        contract.create_character("Mage".to_string());
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("old.testnet".to_string(), None, 30));
+        assert!(contract.save_new_score("old.testnet".to_string(), Some("Mage".to_string()), 12));
+        player_context("old.testnet");
+        assert!(contract.set_info("old.testnet".to_string(), Some(30), Some("Trung".to_string()), None));
+        contract.set_display_name("Trung".to_string());
+        contract.start_profile_transfer("new.testnet".to_string());
+        assert_eq!(Some("new.testnet".to_string()), contract.get_profile_transfer("old.testnet".to_string()));
+
+        player_context("new.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+        assert!(!contract.check_access("old.testnet".to_string()));
+        assert_eq!(None, contract.get_info("old.testnet".to_string()));
+        assert_eq!(None, contract.get_profile_transfer("old.testnet".to_string()));
+        let info = contract.get_info("new.testnet".to_string()).unwrap();
+        assert_eq!(("Trung".to_string(), 30), (info.first_name, info.score));
+        assert_eq!(Some("new.testnet".to_string()), contract.get_account_by_display_name("trung".to_string()));
+        let top_players = contract.get_top_players();
+        assert_eq!(vec![("new.testnet".to_string(), "new.testnet".to_string(), 30), ("new.testnet".to_string(), "Mage".to_string(), 12)],
+                   top_players.into_iter().map(|entry| (entry.account_id, entry.character, entry.score)).collect::<Vec<(String, String, i32)>>());
+        // the first character is still the default one
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("new.testnet".to_string(), None, 40));
+    }
+
+    #[test]
+    #[should_panic(expected = "No profile transfer to accept.")]
+    fn test_accept_profile_transfer_of_another_account() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("old.testnet".to_string());
+        player_context("old.testnet");
+        contract.start_profile_transfer("new.testnet".to_string());
+        player_context("mallory.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unlink the linked accounts before transferring the profile.")]
+    fn test_accept_profile_transfer_linked_since() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("old.testnet".to_string());
+        player_context("old.testnet");
+        contract.start_profile_transfer("new.testnet".to_string());
+        contract.propose_link("mobile.testnet".to_string());
+        player_context("mobile.testnet");
+        contract.confirm_link("old.testnet".to_string());
+        player_context("new.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The new account is linked to another profile.")]
+    fn test_accept_profile_transfer_to_account_linked_since() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("old.testnet".to_string());
+        contract.grant_access("web.testnet".to_string());
+        player_context("old.testnet");
+        contract.start_profile_transfer("new.testnet".to_string());
+        player_context("web.testnet");
+        contract.propose_link("new.testnet".to_string());
+        player_context("new.testnet");
+        contract.confirm_link("web.testnet".to_string());
+        contract.accept_profile_transfer("old.testnet".to_string());
+    }
+
+    #[test]
+    fn test_profile_transfer_keeps_submission_limits() {
+        let mut contract = contract_with_players(&["old.testnet"]);
+        contract.set_board_rules("arena".to_string(), Some(SANE_RULES));
+        assert!(!contract.submit_score("old.testnet".to_string(), None, "arena".to_string(), -5, None));
+        assert!(contract.submit_score("old.testnet".to_string(), None, "arena".to_string(), 50, None));
+        player_context("old.testnet");
+        contract.start_profile_transfer("new.testnet".to_string());
+        player_context("new.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+
+        call_context("jane.testnet", 30 * SECOND, 0);
+        assert!(!contract.submit_score("new.testnet".to_string(), None, "arena".to_string(), 60, None));
+        contract.add_moderator("jane.testnet".to_string());
+        let counts = contract.get_rejection_counts("arena".to_string(), Some("new.testnet".to_string()));
+        assert_eq!((1, 1), (counts.below_minimum, counts.too_frequent));
+        call_context("jane.testnet", 60 * SECOND, 0);
+        assert!(contract.submit_score("new.testnet".to_string(), None, "arena".to_string(), 60, None));
+    }
+
+    #[test]
+    #[should_panic(expected = "The game sessions of the profile should be ended before transferring it.")]
+    fn test_profile_transfer_with_open_session() {
+        let mut contract = contract_with_players(&["old.testnet"]);
+        call_context("old.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        contract.start_session("arena".to_string());
+        contract.start_profile_transfer("new.testnet".to_string());
+        player_context("new.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The committed scores of the profile should be revealed before transferring it.")]
+    fn test_profile_transfer_with_score_commit() {
+        let mut contract = contract_with_players(&["old.testnet"]);
+        contract.set_commit_reveal("arena".to_string(), Some(FINAL_ROUND));
+        player_context("old.testnet");
+        contract.commit_score("arena".to_string(), None, commit_hash(50, "salt", "old.testnet"));
+        contract.start_profile_transfer("new.testnet".to_string());
+        player_context("new.testnet");
+        contract.accept_profile_transfer("old.testnet".to_string());
+    }
+
+    #[test]
+    fn test_linked_accounts() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("web.testnet".to_string());
+        player_context("web.testnet");
+        contract.propose_link("mobile.web.testnet".to_string());
+        player_context("mobile.web.testnet");
+        contract.confirm_link("web.testnet".to_string());
+        assert_eq!("web.testnet".to_string(), contract.get_primary_account("mobile.web.testnet".to_string()));
+        assert_eq!(vec!["mobile.web.testnet".to_string()], contract.get_linked_accounts("web.testnet".to_string()));
+
+        // play from the linked account is ranked under the primary one
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("mobile.web.testnet".to_string(), None, 30));
+        assert_eq!(1, contract.get_top_players().len());
+        assert_eq!(30, contract.get_info("web.testnet".to_string()).unwrap().score);
+        // the reward cooldown is shared
+        assert_eq!(50, contract.get_reward("mobile.web.testnet".to_string()));
+        assert_eq!(0, contract.get_reward("web.testnet".to_string()));
+        assert!(contract.is_recently_rewarded("mobile.web.testnet".to_string()));
+
+        player_context("web.testnet");
+        contract.unlink("mobile.web.testnet".to_string());
+        assert_eq!("mobile.web.testnet".to_string(), contract.get_primary_account("mobile.web.testnet".to_string()));
+        assert!(contract.get_linked_accounts("web.testnet".to_string()).is_empty());
+        player_context("jane.testnet");
+        assert!(!contract.save_new_score("mobile.web.testnet".to_string(), None, 40));
+    }
+
+    #[test]
+    #[should_panic(expected = "No link to confirm.")]
+    fn test_confirm_link_without_proposal() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("web.testnet".to_string());
+        player_context("mallory.testnet");
+        contract.confirm_link("web.testnet".to_string());
+    }
+
+    #[test]
+    fn test_friends_leaderboard() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        for (account_id, score) in [("player.testnet", 20), ("friend.testnet", 30), ("shy.testnet", 40), ("stranger.testnet", 50)] {
+            contract.grant_access(account_id.to_string());
+            assert!(contract.save_new_score(account_id.to_string(), None, score));
+        }
+        for friend_id in ["friend.testnet", "shy.testnet"] {
+            player_context("player.testnet");
+            contract.send_friend_request(friend_id.to_string());
+            player_context(friend_id);
+            assert_eq!(vec!["player.testnet".to_string()], contract.get_friend_requests(friend_id.to_string()));
+            contract.accept_friend_request("player.testnet".to_string());
+        }
+        assert!(contract.get_friend_requests("shy.testnet".to_string()).is_empty());
+        contract.set_privacy(PrivacySettings { info: Visibility::Public, rank: Visibility::Hidden });
+
+        let leaderboard = contract.get_friends_leaderboard("player.testnet".to_string(), 0, 10);
+        assert_eq!(vec![("friend.testnet".to_string(), 30), ("player.testnet".to_string(), 20)],
+                   leaderboard.into_iter().map(|entry| (entry.account_id, entry.score)).collect::<Vec<(String, i32)>>());
+        assert_eq!("player.testnet", contract.get_friends_leaderboard("player.testnet".to_string(), 1, 10)[0].account_id);
+
+        player_context("friend.testnet");
+        contract.remove_friend("player.testnet".to_string());
+        assert_eq!(vec!["shy.testnet".to_string()], contract.get_friends("player.testnet".to_string()));
+        // a revoked profile leaves the lists of its friends
+        player_context("shy.testnet");
+        contract.revoke_access("shy.testnet".to_string());
+        assert!(contract.get_friends("player.testnet".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_friends_leaderboard_privacy() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        for (account_id, score) in [("player.testnet", 20), ("friend.testnet", 30)] {
+            contract.grant_access(account_id.to_string());
+            assert!(contract.save_new_score(account_id.to_string(), None, score));
+        }
+        player_context("player.testnet");
+        contract.send_friend_request("friend.testnet".to_string());
+        player_context("friend.testnet");
+        contract.accept_friend_request("player.testnet".to_string());
+        contract.set_privacy(PrivacySettings { info: Visibility::Public, rank: Visibility::FriendsOnly });
+        player_context("player.testnet");
+        contract.set_privacy(PrivacySettings { info: Visibility::Public, rank: Visibility::Hidden });
+
+        // anyone
+        assert!(contract.get_friends_leaderboard("player.testnet".to_string(), 0, 10).is_empty());
+        // the player itself, a friend of friend.testnet
+        let leaderboard = contract.get_friends_leaderboard_as_caller(0, 10);
+        assert_eq!(vec![("friend.testnet".to_string(), 30), ("player.testnet".to_string(), 20)],
+                   leaderboard.into_iter().map(|entry| (entry.account_id, entry.score)).collect::<Vec<(String, i32)>>());
+        // friend.testnet does not see the hidden rank of its friend
+        player_context("friend.testnet");
+        assert_eq!(vec!["friend.testnet".to_string()],
+                   contract.get_friends_leaderboard_as_caller(0, 10).into_iter().map(|entry| entry.account_id).collect::<Vec<AccountId>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a friend.")]
+    fn test_remove_unknown_friend() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.remove_friend("stranger.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "No friend request to decline.")]
+    fn test_decline_unknown_friend_request() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.decline_friend_request("stranger.testnet".to_string());
+    }
+
+    #[test]
+    fn test_crossed_friend_requests() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.grant_access("friend.testnet".to_string());
+        player_context("player.testnet");
+        contract.send_friend_request("friend.testnet".to_string());
+        player_context("friend.testnet");
+        contract.send_friend_request("player.testnet".to_string());
+        assert_eq!(vec!["friend.testnet".to_string()], contract.get_friends("player.testnet".to_string()));
+        assert!(contract.get_friend_requests("friend.testnet".to_string()).is_empty());
+    }
+
+    fn guild_contract() -> ProfileState {
+        let mut contract = contract_with_players(&["founder.testnet", "officer.testnet", "member.testnet", "rival.testnet"]);
+        for (account_id, score) in [("founder.testnet", 10), ("officer.testnet", 40), ("member.testnet", 20), ("rival.testnet", 50)] {
+            assert!(contract.save_new_score(account_id.to_string(), None, score));
+        }
+        call_context("founder.testnet", 0, DEFAULT_GUILD_DEPOSIT);
+        assert_eq!(0, contract.create_guild("Knights".to_string()));
+        call_context("founder.testnet", 0, 0);
+        contract.invite_to_guild("officer.testnet".to_string());
+        call_context("officer.testnet", 0, 0);
+        contract.accept_guild_invite(0);
+        call_context("founder.testnet", 0, 0);
+        contract.set_guild_officer("officer.testnet".to_string(), true);
+        call_context("member.testnet", 0, 0);
+        contract.request_to_join_guild(0);
+        call_context("officer.testnet", 0, 0);
+        contract.accept_join_request("member.testnet".to_string());
+        call_context("rival.testnet", 0, DEFAULT_GUILD_DEPOSIT);
+        assert_eq!(1, contract.create_guild("Rivals".to_string()));
+        contract
+    }
+
+    #[test]
+    fn test_guild_leaderboard() {
+        let mut contract = guild_contract();
+        assert_eq!(Some(0), contract.get_guild_of("member.testnet".to_string()));
+        assert_eq!(vec![(0, 70), (1, 50)], contract.get_guild_leaderboard(0, 10).into_iter()
+            .map(|entry| (entry.guild_id, entry.score)).collect::<Vec<(u64, i64)>>());
+        // the board follows the best score of the members
+        call_context("jane.testnet", 0, 0);
+        assert!(contract.save_new_score("member.testnet".to_string(), None, 35));
+        assert_eq!(85, contract.get_guild_leaderboard(0, 1)[0].score);
+
+        contract.set_guild_rules(U128(DEFAULT_GUILD_DEPOSIT), GuildScoreRule::AverageOfTop(2));
+        assert_eq!(vec![(0, 37), (1, 25)], contract.get_guild_leaderboard(0, 10).into_iter()
+            .map(|entry| (entry.guild_id, entry.score)).collect::<Vec<(u64, i64)>>());
+        contract.set_guild_rules(U128(DEFAULT_GUILD_DEPOSIT), GuildScoreRule::Best);
+        assert_eq!(vec![(1, 50), (0, 40)], contract.get_guild_leaderboard(0, 10).into_iter()
+            .map(|entry| (entry.guild_id, entry.score)).collect::<Vec<(u64, i64)>>());
+    }
+
+    #[test]
+    fn test_guild_score_after_character_deleted() {
+        let mut contract = guild_contract();
+        call_context("member.testnet", 0, 0);
+        contract.create_character("Mage".to_string());
+        call_context("jane.testnet", 0, 0);
+        assert!(contract.save_new_score("member.testnet".to_string(), Some("Mage".to_string()), 35));
+        assert_eq!(85, contract.get_guild_leaderboard(0, 1)[0].score);
+        call_context("member.testnet", 0, 0);
+        contract.delete_character("Mage".to_string());
+        assert_eq!(70, contract.get_guild_leaderboard(0, 1)[0].score);
+    }
+
+    #[test]
+    #[should_panic(expected = "Guild members should have a profile.")]
+    fn test_accept_guild_invite_without_profile() {
+        let mut contract = guild_contract();
+        call_context("founder.testnet", 0, 0);
+        contract.invite_to_guild("rival.testnet".to_string());
+        call_context("rival.testnet", 0, 0);
+        contract.leave_guild();
+        contract.revoke_access("rival.testnet".to_string());
+        contract.accept_guild_invite(0);
+    }
+
+    #[test]
+    fn test_guild_with_a_profile_named_like_the_state() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("guilds".to_string());
+        call_context("guilds", 0, DEFAULT_GUILD_DEPOSIT);
+        assert_eq!(0, contract.create_guild("Knights".to_string()));
+        assert_eq!("Knights", contract.get_guild(0).unwrap().name);
+        assert_eq!(1, contract.get_guild_leaderboard(0, 10).len());
+        assert_eq!(1, contract.get_characters("guilds".to_string()).len());
+    }
+
+    #[test]
+    fn test_guild_membership() {
+        let mut contract = guild_contract();
+        call_context("officer.testnet", 0, 0);
+        contract.kick_from_guild("member.testnet".to_string());
+        assert_eq!(None, contract.get_guild_of("member.testnet".to_string()));
+
+        // the founder hands over the guild to its officer
+        call_context("founder.testnet", 0, 0);
+        contract.leave_guild();
+        let guild = contract.get_guild(0).unwrap();
+        assert_eq!(vec![GuildMember {
+            account_id: "officer.testnet".to_string(),
+            role: GuildRole::Founder,
+            best_score: 40,
+        }], guild.members);
+
+        // the last member disbands it
+        call_context("officer.testnet", 0, 0);
+        contract.leave_guild();
+        assert_eq!(None, contract.get_guild(0));
+        call_context("officer.testnet", 0, DEFAULT_GUILD_DEPOSIT);
+        assert_eq!(2, contract.create_guild("knights".to_string()));
+    }
+
+    #[test]
+    fn test_disbanded_guild_refunds_founder_deposit() {
+        let mut contract = guild_contract();
+        call_context("founder.testnet", 0, 0);
+        contract.leave_guild();
+        call_context("officer.testnet", 0, 0);
+        contract.leave_guild();
+        // the last member never paid the deposit, the founder gets it back
+        call_context("member.testnet", 0, 0);
+        contract.leave_guild();
+        assert_eq!(None, contract.get_guild(0));
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(1, receipts.len());
+        assert!(format!("{:?}", receipts[0]).contains(r#"receiver_id: "founder.testnet""#));
+    }
+
+    #[test]
+    fn test_guild_treasury() {
+        let mut contract = guild_contract();
+        call_context("founder.testnet", 0, 0);
+        contract.set_guild_treasury_rules(20, 60);
+        // 20 percent of the reward of 50 points
+        assert_eq!(40, contract.get_reward("officer.testnet".to_string()));
+        assert_eq!(10, contract.get_guild(0).unwrap().treasury);
+
+        call_context("officer.testnet", 0, 0);
+        let proposal_id = contract.propose_treasury_spend("artist.testnet".to_string(), 8, "Guild banner".to_string());
+        contract.vote_treasury_spend(proposal_id, true);
+        assert_eq!(ProposalStatus::Open, contract.get_treasury_proposal(proposal_id).unwrap().status);
+        // 2 approvals out of 3 members reach the quorum of 60 percent
+        call_context("member.testnet", 0, 0);
+        contract.vote_treasury_spend(proposal_id, true);
+        assert_eq!(ProposalStatus::Executed, contract.get_treasury_proposal(proposal_id).unwrap().status);
+        assert_eq!(2, contract.get_guild(0).unwrap().treasury);
+
+        call_context("officer.testnet", 0, 0);
+        let proposal_id = contract.propose_treasury_spend("officer.testnet".to_string(), 2, String::new());
+        for voter_id in ["founder.testnet", "member.testnet"] {
+            call_context(voter_id, 0, 0);
+            contract.vote_treasury_spend(proposal_id, false);
+        }
+        assert_eq!(ProposalStatus::Rejected, contract.get_treasury_proposal(proposal_id).unwrap().status);
+
+        // the last member leaving gets what is left of the treasury
+        for account_id in ["member.testnet", "founder.testnet", "officer.testnet"] {
+            call_context(account_id, 0, 0);
+            contract.leave_guild();
+        }
+        assert!(get_logs().contains(&r#"EVENT_JSON:{"data":{"amount":2,"balance":0,"guild_id":0,"proposal_id":null,"receiver_id":"officer.testnet"},"event":"guild_treasury_debit","standard":"leaderboard","version":"1.0.0"}"#.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only members of the guild can vote.")]
+    fn test_vote_treasury_spend_of_another_guild() {
+        let mut contract = guild_contract();
+        call_context("founder.testnet", 0, 0);
+        contract.set_guild_treasury_rules(50, 50);
+        assert_eq!(25, contract.get_reward("member.testnet".to_string()));
+        call_context("founder.testnet", 0, 0);
+        let proposal_id = contract.propose_treasury_spend("founder.testnet".to_string(), 25, String::new());
+        call_context("rival.testnet", 0, 0);
+        contract.vote_treasury_spend(proposal_id, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not allowed to kick this member.")]
+    fn test_officer_can_not_kick_officer() {
+        let mut contract = guild_contract();
+        call_context("founder.testnet", 0, 0);
+        contract.set_guild_officer("member.testnet".to_string(), true);
+        call_context("officer.testnet", 0, 0);
+        contract.kick_from_guild("member.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Creating a guild requires a deposit of 1000000000000000000000000 yoctoNEAR.")]
+    fn test_create_guild_without_deposit() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("founder.testnet".to_string());
+        call_context("founder.testnet", 0, 0);
+        contract.create_guild("Knights".to_string());
+    }
+
+    #[test]
+    fn test_region_leaderboards() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        for (account_id, region, score) in [("a.testnet", "vn", 30), ("b.testnet", "VN", 10), ("c.testnet", "FR", 25)] {
+            contract.grant_access(account_id.to_string());
+            assert!(contract.save_new_score(account_id.to_string(), None, score));
+            player_context(account_id);
+            contract.set_region(region.to_string());
+            player_context("jane.testnet");
+        }
+        assert_eq!(Some("VN".to_string()), contract.get_region("b.testnet".to_string()));
+        assert_eq!(vec![("a.testnet".to_string(), 30), ("b.testnet".to_string(), 10)],
+                   contract.get_top_players_by_region("vn".to_string(), 0, 10).into_iter()
+                       .map(|entry| (entry.account_id, entry.score)).collect::<Vec<(String, i32)>>());
+
+        let regions = |contract: &ProfileState, ranking| contract.get_region_leaderboard(ranking, 0, 10).into_iter()
+            .map(|entry| (entry.region, entry.total_score, entry.average_score)).collect::<Vec<(String, i64, i64)>>();
+        assert_eq!(vec![("VN".to_string(), 40, 20), ("FR".to_string(), 25, 25)], regions(&contract, RegionRanking::Total));
+        assert_eq!(vec![("FR".to_string(), 25, 25), ("VN".to_string(), 40, 20)], regions(&contract, RegionRanking::Average));
+
+        // the stats follow the best scores and the moves
+        assert!(contract.save_new_score("b.testnet".to_string(), None, 20));
+        player_context("a.testnet");
+        contract.set_region("FR".to_string());
+        assert_eq!(vec![("FR".to_string(), 55, 27), ("VN".to_string(), 20, 20)], regions(&contract, RegionRanking::Total));
+        player_context("c.testnet");
+        contract.revoke_access("c.testnet".to_string());
+        assert_eq!(vec![("FR".to_string(), 30, 30), ("VN".to_string(), 20, 20)], regions(&contract, RegionRanking::Total));
+        assert_eq!(vec![("a.testnet".to_string(), 30)],
+                   contract.get_top_players_by_region("FR".to_string(), 0, 10).into_iter()
+                       .map(|entry| (entry.account_id, entry.score)).collect::<Vec<(String, i32)>>());
+    }
+
+    #[test]
+    fn test_region_follows_characters() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 10));
+        player_context("player.testnet");
+        contract.set_region("VN".to_string());
+        contract.create_character("Mage".to_string());
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("player.testnet".to_string(), Some("Mage".to_string()), 40));
+        player_context("player.testnet");
+        contract.rename_character("Mage".to_string(), "Archmage".to_string());
+        let top_players = |contract: &ProfileState| contract.get_top_players_by_region("VN".to_string(), 0, 10).into_iter()
+            .map(|entry| (entry.character, entry.score)).collect::<Vec<(String, i32)>>();
+        assert_eq!(vec![("Archmage".to_string(), 40), ("player.testnet".to_string(), 10)], top_players(&contract));
+        assert_eq!("player.testnet", contract.get_top_players_by_region("vn".to_string(), 1, 1)[0].character);
+        assert_eq!(40, contract.get_region_leaderboard(RegionRanking::Total, 0, 1)[0].total_score);
+
+        // the region stats go back to the best remaining character
+        contract.delete_character("Archmage".to_string());
+        assert_eq!(vec![("player.testnet".to_string(), 10)], top_players(&contract));
+        assert_eq!(10, contract.get_region_leaderboard(RegionRanking::Total, 0, 1)[0].total_score);
+    }
+
+    #[test]
+    #[should_panic(expected = "The region can not be changed more than 2 times per season.")]
+    fn test_region_changes_per_season() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        contract.set_region("VN".to_string());
+        contract.set_region("FR".to_string());
+        contract.set_region("DE".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown region XX, use an ISO 3166-1 alpha-2 code.")]
+    fn test_unknown_region() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        contract.set_region("XX".to_string());
+    }
+
+    #[test]
+    fn test_score_history() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.set_history_depth(3);
+        let replay = ReplayProof { hash: "ab".repeat(32), cid: Some("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string()) };
+        for (timestamp, board_id, score) in [(1, "arena", 10), (2, "arena", 30), (3, "race", 5), (4, "arena", 20), (5, "race", 8)] {
+            call_context("jane.testnet", timestamp, 0);
+            let replay = if timestamp == 2 { Some(replay.clone()) } else { None };
+            contract.submit_score("player.testnet".to_string(), None, board_id.to_string(), score, replay);
+        }
+
+        // only the 3 most recent submissions are kept
+        assert_eq!(vec![(5, 8), (4, 20), (3, 5)], contract.get_score_history("player.testnet".to_string(), None, 0, 10).into_iter()
+            .map(|record| (record.timestamp, record.score)).collect::<Vec<(u64, i32)>>());
+        assert_eq!(4, contract.get_score_history("player.testnet".to_string(), None, 1, 1)[0].timestamp);
+        let bests = contract.get_personal_bests("player.testnet".to_string(), None);
+        assert_eq!((Some(&30), Some(&8)), (bests.get("arena"), bests.get("race")));
+        let improvement = contract.get_score_improvement("player.testnet".to_string(), None, "arena".to_string()).unwrap();
+        assert_eq!((10, 30, 20, 3, 20), (improvement.first.score, improvement.best.score, improvement.latest.score, improvement.submissions, improvement.improvement));
+        assert_eq!(Some(replay), improvement.best.replay);
+
+        // a lower depth drops the oldest records on the next submission
+        contract.set_history_depth(2);
+        call_context("jane.testnet", 6, 0);
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 40));
+        assert_eq!(vec![(6, DEFAULT_BOARD_ID.to_string()), (5, "race".to_string())],
+                   contract.get_score_history("player.testnet".to_string(), None, 0, 10).into_iter()
+                       .map(|record| (record.timestamp, record.board_id)).collect::<Vec<(u64, String)>>());
+    }
+
+    #[test]
+    fn test_board_leaderboard() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        contract.create_character("Knight".to_string());
+        assert!(contract.submit_score("jane.testnet".to_string(), Some("Knight".to_string()), "arena".to_string(), 30, None));
+        assert!(contract.submit_score("jane.testnet".to_string(), None, "arena".to_string(), 20, None));
+        assert!(!contract.submit_score("jane.testnet".to_string(), None, "arena".to_string(), 10, None));
+        // the other boards don't rank the profile
+        assert!(contract.get_top_players().iter().all(|entry| entry.score == 0));
+        assert_eq!(vec![("Knight".to_string(), 30), ("jane.testnet".to_string(), 20)],
+                   contract.get_board_leaderboard("arena".to_string(), 0, 10).into_iter()
+                       .map(|entry| (entry.character, entry.score)).collect::<Vec<(String, i32)>>());
+
+        contract.rename_character("Knight".to_string(), "Paladin".to_string());
+        assert_eq!("Paladin", contract.get_board_leaderboard("arena".to_string(), 0, 1)[0].character);
+        contract.delete_character("Paladin".to_string());
+        assert_eq!(1, contract.get_board_leaderboard("arena".to_string(), 0, 10).len());
+        assert!(contract.save_new_score("jane.testnet".to_string(), None, 5));
+        assert_eq!(5, contract.get_top_players()[0].score);
+        assert_eq!(5, contract.get_board_leaderboard(DEFAULT_BOARD_ID.to_string(), 0, 10)[0].score);
+    }
+
+    #[test]
+    #[should_panic(expected = "A character can not play more than 20 boards.")]
+    fn test_submit_score_too_many_boards() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("jane.testnet".to_string());
+        for board in 0..=MAX_BOARDS_PER_CHARACTER {
+            contract.submit_score("jane.testnet".to_string(), None, format!("board-{}", board), 10, None);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Replay hash should be a hex encoded SHA-256.")]
+    fn test_submit_score_invalid_replay_hash() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.submit_score("player.testnet".to_string(), None, "arena".to_string(), 10, Some(ReplayProof { hash: "replay".to_string(), cid: None }));
+    }
+
+    const SECOND: u64 = 1_000_000_000;
+
+    // attested score of a session of player.testnet on "arena", valid for the longest session
+    fn submit_session(contract: &mut ProfileState, session_id: u64, score: i32, nonce: u64) -> bool {
+        let attestation = ScoreAttestation { expiry: U64(3600 * SECOND), ..attestation(score, nonce) };
+        contract.submit_session_score(session_id, attestation.clone(), None, sign_attestation(&attestation), None)
+    }
+
+    #[test]
+    fn test_game_sessions() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let played = contract.start_session("arena".to_string());
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let abandoned = contract.start_session("arena".to_string());
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let expired = contract.start_session("arena".to_string());
+
+        call_context("player.testnet", 60 * SECOND, 0);
+        assert!(submit_session(&mut contract, played, 25, 1));
+        assert_eq!(25, contract.get_personal_bests("player.testnet".to_string(), None)["arena"]);
+        contract.end_session(played);
+        contract.end_session(abandoned);
+        assert_eq!(None, contract.get_session(played));
+
+        // nothing expired yet
+        assert_eq!(0, contract.clean_expired_sessions(10));
+        call_context("cleaner.testnet", 3601 * SECOND, 0);
+        assert_eq!(1, contract.clean_expired_sessions(10));
+        assert_eq!(None, contract.get_session(expired));
+        assert_eq!(SessionStats { started: 3, submitted: 1, abandoned: 2, open: 0 }, contract.get_session_stats("arena".to_string()));
+    }
+
+    #[test]
+    fn test_reporters() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_reporter("server.testnet".to_string());
+        player_context("server.testnet");
+        assert!(contract.submit_score("player.testnet".to_string(), None, "arena".to_string(), 10, None));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 20));
+
+        player_context("jane.testnet");
+        contract.remove_reporter("server.testnet".to_string());
+        assert!(contract.get_reporters().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner or a reporter can submit scores directly.")]
+    fn test_submit_own_score() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.save_new_score("player.testnet".to_string(), None, 1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "The score of the session was already submitted.")]
+    fn test_submit_session_score_twice() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let session_id = contract.start_session("arena".to_string());
+        call_context("player.testnet", 60 * SECOND, 0);
+        submit_session(&mut contract, session_id, 25, 1);
+        submit_session(&mut contract, session_id, 30, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "The session is too short.")]
+    fn test_submit_session_score_too_soon() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let session_id = contract.start_session("arena".to_string());
+        call_context("player.testnet", SECOND, 0);
+        submit_session(&mut contract, session_id, 25, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is not signed by a game server.")]
+    fn test_submit_session_score_unsigned() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let session_id = contract.start_session("arena".to_string());
+        call_context("player.testnet", 60 * SECOND, 0);
+        // signed for a lower score
+        let signature = sign_attestation(&attestation(25, 1));
+        contract.submit_session_score(session_id, attestation(1000, 1), None, signature, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is for another board.")]
+    fn test_submit_session_score_of_other_board() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, DEFAULT_SESSION_DEPOSIT);
+        let session_id = contract.start_session("race".to_string());
+        call_context("player.testnet", 60 * SECOND, 0);
+        submit_session(&mut contract, session_id, 25, 1);
+    }
+
+    fn server_keypair() -> (ed25519_dalek::ExpandedSecretKey, ed25519_dalek::PublicKey, Base58PublicKey) {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let mut key = vec![0];
+        key.extend_from_slice(public.as_bytes());
+        (ed25519_dalek::ExpandedSecretKey::from(&secret), public, Base58PublicKey(key))
+    }
+
+    fn sign_attestation(attestation: &ScoreAttestation) -> Base64VecU8 {
+        let (secret, public, _) = server_keypair();
+        Base64VecU8(secret.sign(&(env::current_account_id(), attestation).try_to_vec().unwrap(), &public).to_bytes().to_vec())
+    }
+
+    fn attestation(score: i32, nonce: u64) -> ScoreAttestation {
+        ScoreAttestation {
+            account_id: "player.testnet".to_string(),
+            board_id: "arena".to_string(),
+            score,
+            nonce: U64(nonce),
+            expiry: U64(100 * SECOND),
+        }
+    }
+
+    #[test]
+    fn test_attested_scores() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        assert_eq!(vec![server_keypair().2], contract.get_server_keys());
+
+        call_context("player.testnet", 0, 0);
+        assert!(contract.submit_attested_score(attestation(25, 1), None, sign_attestation(&attestation(25, 1))));
+        assert!(contract.submit_attested_score(attestation(30, 2), None, sign_attestation(&attestation(30, 2))));
+        let later = ScoreAttestation { expiry: U64(200 * SECOND), ..attestation(10, 3) };
+        assert!(!contract.submit_attested_score(later.clone(), None, sign_attestation(&later)));
+        assert_eq!(30, contract.get_personal_bests("player.testnet".to_string(), None)["arena"]);
+
+        // only the expired nonces are removed
+        call_context("player.testnet", 101 * SECOND, 0);
+        assert_eq!(2, contract.clean_used_nonces(10));
+        assert_eq!(0, contract.clean_used_nonces(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is not signed by a game server.")]
+    fn test_attested_score_other_contract() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        let (secret, public, _) = server_keypair();
+        let signature = secret.sign(&("other.testnet".to_string(), attestation(25, 1)).try_to_vec().unwrap(), &public);
+        call_context("player.testnet", 0, 0);
+        contract.submit_attested_score(attestation(25, 1), None, Base64VecU8(signature.to_bytes().to_vec()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Scores should be signed by a game server, see `submit_attested_score`.")]
+    fn test_save_new_score_without_signature() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        contract.save_new_score("player.testnet".to_string(), None, 25);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation was already used.")]
+    fn test_attested_score_replay() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, 0);
+        contract.submit_attested_score(attestation(25, 1), None, sign_attestation(&attestation(25, 1)));
+        contract.submit_attested_score(attestation(25, 1), None, sign_attestation(&attestation(25, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is not signed by a game server.")]
+    fn test_attested_score_tampered() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        call_context("player.testnet", 0, 0);
+        contract.submit_attested_score(attestation(9000, 1), None, sign_attestation(&attestation(25, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is not signed by a game server.")]
+    fn test_attested_score_removed_key() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.add_server_key(server_keypair().2);
+        contract.remove_server_key(server_keypair().2);
+        call_context("player.testnet", 0, 0);
+        contract.submit_attested_score(attestation(25, 1), None, sign_attestation(&attestation(25, 1)));
+    }
+
+    fn commit_hash(score: i32, salt: &str, account_id: &str) -> String {
+        env::sha256(&(score, salt, account_id).try_to_vec().unwrap()).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    const FINAL_ROUND: CommitRevealRules = CommitRevealRules { commit_end: U64(100 * SECOND), reveal_end: U64(200 * SECOND) };
+
+    // attested reveal of the score of player.testnet on "final", salted with "pepper"
+    fn reveal(contract: &mut ProfileState, score: i32, nonce: u64) -> bool {
+        let attestation = ScoreAttestation { board_id: "final".to_string(), expiry: U64(300 * SECOND), ..attestation(score, nonce) };
+        contract.reveal_score(attestation.clone(), None, "pepper".to_string(), sign_attestation(&attestation), None)
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_server_key(server_keypair().2);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(40, "pepper", "player.testnet"));
+        // committed scores are not ranked until revealed
+        assert_eq!(None, contract.get_personal_bests("player.testnet".to_string(), None).get("final"));
+
+        call_context("player.testnet", 150 * SECOND, 0);
+        assert!(reveal(&mut contract, 40, 1));
+        assert_eq!(40, contract.get_personal_bests("player.testnet".to_string(), None)["final"]);
+        assert_eq!(None, contract.get_score_commit("player.testnet".to_string(), None, "final".to_string()));
+    }
+
+    #[test]
+    fn test_clean_expired_commits() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(40, "pepper", "player.testnet"));
+        call_context("player.testnet", 150 * SECOND, 0);
+        assert_eq!(0, contract.clean_expired_commits(10));
+        call_context("player.testnet", 201 * SECOND, 0);
+        assert_eq!(1, contract.clean_expired_commits(10));
+        assert_eq!(None, contract.get_score_commit("player.testnet".to_string(), None, "final".to_string()));
+    }
+
+    #[test]
+    fn test_clean_commits_of_extended_round() {
+        let mut contract = contract_with_players(&["player.testnet", "rival.testnet"]);
+        contract.add_server_key(server_keypair().2);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(40, "pepper", "player.testnet"));
+        contract.commit_score("final".to_string(), None, commit_hash(50, "pepper", "player.testnet"));
+        call_context("rival.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(30, "salt", "rival.testnet"));
+        call_context("jane.testnet", 150 * SECOND, 0);
+        contract.set_commit_reveal("final".to_string(), Some(CommitRevealRules { reveal_end: U64(300 * SECOND), ..FINAL_ROUND }));
+
+        // the round is still open, the commits are kept
+        call_context("player.testnet", 250 * SECOND, 0);
+        assert_eq!(0, contract.clean_expired_commits(10));
+        assert!(reveal(&mut contract, 50, 1));
+        call_context("rival.testnet", 301 * SECOND, 0);
+        assert_eq!(1, contract.clean_expired_commits(10));
+        assert_eq!(0, contract.clean_expired_commits(10));
+    }
+
+    #[test]
+    fn test_submit_score_after_reveal_deadline() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("jane.testnet", 201 * SECOND, 0);
+        assert!(contract.submit_score("player.testnet".to_string(), None, "final".to_string(), 40, None));
+    }
+
+    #[test]
+    #[should_panic(expected = "The score doesn't match the commit.")]
+    fn test_reveal_other_score() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(40, "pepper", "player.testnet"));
+        call_context("player.testnet", 150 * SECOND, 0);
+        reveal(&mut contract, 90, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attestation is not signed by a game server.")]
+    fn test_reveal_unsigned_score() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_server_key(server_keypair().2);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(1000, "pepper", "player.testnet"));
+        call_context("player.testnet", 150 * SECOND, 0);
+        // the committed score was never attested, the signature of another one does not match
+        let attestation = ScoreAttestation { board_id: "final".to_string(), expiry: U64(300 * SECOND), ..attestation(1000, 1) };
+        let signature = sign_attestation(&ScoreAttestation { score: 40, ..attestation.clone() });
+        contract.reveal_score(attestation, None, "pepper".to_string(), signature, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The reveal period has not started.")]
+    fn test_reveal_during_commit_period() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        call_context("player.testnet", 0, 0);
+        contract.commit_score("final".to_string(), None, commit_hash(40, "pepper", "player.testnet"));
+        reveal(&mut contract, 40, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Scores of board final should be committed, see `commit_score`.")]
+    fn test_submit_score_to_commit_reveal_board() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_commit_reveal("final".to_string(), Some(FINAL_ROUND));
+        contract.submit_score("player.testnet".to_string(), None, "final".to_string(), 40, None);
+    }
+
+    const CHALLENGE_TOP_2: ChallengeRules = ChallengeRules { top: 2, window: 100 * SECOND, bond: U128(DEFAULT_DISPUTE_BOND) };
+
+    // 0 when the account is not ranked on the board
+    fn ranked_score(contract: &ProfileState, board_id: &str, account_id: &str) -> i32 {
+        contract.get_board_leaderboard(board_id.to_string(), 0, 100).into_iter().find(|entry| entry.account_id == account_id).map_or(0, |entry| entry.score)
+    }
+
+    #[test]
+    fn test_provisional_scores() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        assert!(contract.save_new_score("first.testnet".to_string(), None, 50));
+        assert_eq!(0, ranked_score(&contract, DEFAULT_BOARD_ID, "first.testnet"));
+        let provisional = contract.get_provisional_score(0).unwrap();
+        assert_eq!(("first.testnet".to_string(), 50), (provisional.account_id, provisional.score));
+
+        call_context("first.testnet", 101 * SECOND, 0);
+        assert!(contract.finalize_score(0));
+        assert_eq!(50, ranked_score(&contract, DEFAULT_BOARD_ID, "first.testnet"));
+        assert_eq!(None, contract.get_provisional_score(0));
+    }
+
+    #[test]
+    fn test_provisional_score_only_in_top() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        contract.save_new_score("second.testnet".to_string(), None, 40);
+        call_context("jane.testnet", 101 * SECOND, 0);
+        contract.finalize_score(0);
+        contract.finalize_score(1);
+        // below the top 2, the score is ranked right away
+        assert!(contract.save_new_score("third.testnet".to_string(), None, 30));
+        assert_eq!(30, ranked_score(&contract, DEFAULT_BOARD_ID, "third.testnet"));
+        assert!(contract.get_provisional_scores(0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_disputes() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.add_moderator("moderator.testnet".to_string());
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        contract.save_new_score("second.testnet".to_string(), None, 60);
+        call_context("third.testnet", SECOND, DEFAULT_DISPUTE_BOND);
+        contract.dispute_score(0, "Impossible run".to_string());
+        contract.dispute_score(1, "".to_string());
+        assert_eq!("third.testnet", contract.get_provisional_score(0).unwrap().dispute.unwrap().challenger_id);
+
+        call_context("moderator.testnet", 200 * SECOND, 0);
+        contract.resolve_dispute(0, false);
+        contract.resolve_dispute(1, true);
+        assert_eq!(50, ranked_score(&contract, DEFAULT_BOARD_ID, "first.testnet"));
+        assert_eq!(0, ranked_score(&contract, DEFAULT_BOARD_ID, "second.testnet"));
+        assert!(contract.get_provisional_scores(0, 10).is_empty());
+        // the removed score is not kept
+        assert!(contract.get_score_history("second.testnet".to_string(), None, 0, 10).is_empty());
+        assert!(contract.get_personal_bests("second.testnet".to_string(), None).is_empty());
+        assert_eq!(1, contract.get_board_distribution(DEFAULT_BOARD_ID.to_string()).unwrap().count);
+    }
+
+    #[test]
+    fn test_one_provisional_score_per_board() {
+        let mut contract = contract_with_players(&["first.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        assert!(contract.save_new_score("first.testnet".to_string(), None, 50));
+        assert!(!contract.save_new_score("first.testnet".to_string(), None, 40));
+        assert!(contract.save_new_score("first.testnet".to_string(), None, 70));
+        assert_eq!(vec![70], contract.get_provisional_scores(0, 10).into_iter()
+            .map(|(_, provisional)| provisional.score).collect::<Vec<i32>>());
+        // the replaced score is saved, not ranked
+        assert_eq!(vec![50, 40], contract.get_score_history("first.testnet".to_string(), None, 0, 10).into_iter()
+            .map(|record| record.score).collect::<Vec<i32>>());
+        assert_eq!(0, ranked_score(&contract, DEFAULT_BOARD_ID, "first.testnet"));
+
+        call_context("first.testnet", 101 * SECOND, 0);
+        assert!(contract.finalize_score(1));
+        assert_eq!(70, ranked_score(&contract, DEFAULT_BOARD_ID, "first.testnet"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The provisional score 0 of the character is disputed, see `resolve_dispute`.")]
+    fn test_replace_disputed_provisional_score() {
+        let mut contract = contract_with_players(&["first.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        call_context("third.testnet", SECOND, DEFAULT_DISPUTE_BOND);
+        contract.dispute_score(0, "Impossible run".to_string());
+        call_context("jane.testnet", 2 * SECOND, 0);
+        contract.save_new_score("first.testnet".to_string(), None, 70);
+    }
+
+    #[test]
+    #[should_panic(expected = "The score is disputed, see `resolve_dispute`.")]
+    fn test_finalize_disputed_score() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        call_context("third.testnet", SECOND, DEFAULT_DISPUTE_BOND);
+        contract.dispute_score(0, "Impossible run".to_string());
+        call_context("first.testnet", 101 * SECOND, 0);
+        contract.finalize_score(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The challenge window is over.")]
+    fn test_dispute_after_challenge_window() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        call_context("third.testnet", 101 * SECOND, DEFAULT_DISPUTE_BOND);
+        contract.dispute_score(0, "Impossible run".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can call this method.")]
+    fn test_resolve_dispute_not_moderator() {
+        let mut contract = contract_with_players(&["first.testnet", "second.testnet", "third.testnet"]);
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        contract.save_new_score("first.testnet".to_string(), None, 50);
+        call_context("third.testnet", SECOND, DEFAULT_DISPUTE_BOND);
+        contract.dispute_score(0, "Impossible run".to_string());
+        contract.resolve_dispute(0, true);
+    }
+
+    const SANE_RULES: BoardRules = BoardRules {
+        min_score: Some(0),
+        max_score: Some(1000),
+        max_improvement: Some(100),
+        min_interval: Some(60 * SECOND),
+        daily_cap: Some(3),
+    };
+
+    fn submit_arena_score(contract: &mut ProfileState, block_timestamp: u64, score: i32) -> bool {
+        call_context("jane.testnet", block_timestamp, 0);
+        contract.submit_score("player.testnet".to_string(), None, "arena".to_string(), score, None)
+    }
+
+    #[test]
+    fn test_board_rules() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_moderator("moderator.testnet".to_string());
+        contract.set_board_rules("arena".to_string(), Some(SANE_RULES));
+        assert!(!submit_arena_score(&mut contract, 0, -5));
+        assert!(!submit_arena_score(&mut contract, 0, i32::MAX));
+        assert!(submit_arena_score(&mut contract, 0, 50));
+        assert!(!submit_arena_score(&mut contract, 30 * SECOND, 60));
+        assert!(!submit_arena_score(&mut contract, 60 * SECOND, 500));
+        assert!(submit_arena_score(&mut contract, 60 * SECOND, 150));
+        assert!(submit_arena_score(&mut contract, 120 * SECOND, 200));
+        assert!(!submit_arena_score(&mut contract, 180 * SECOND, 250));
+        // the daily cap is reset the next day
+        assert!(submit_arena_score(&mut contract, DAY, 250));
+        assert_eq!(250, contract.get_personal_bests("player.testnet".to_string(), None)["arena"]);
+        assert_eq!(4, contract.get_score_history("player.testnet".to_string(), None, 0, 10).len());
+
+        call_context("moderator.testnet", DAY, 0);
+        let counts = RejectionCounts { below_minimum: 1, above_maximum: 1, improvement_too_large: 1, too_frequent: 1, daily_cap_reached: 1 };
+        assert_eq!(counts, contract.get_rejection_counts("arena".to_string(), None));
+        assert_eq!(counts, contract.get_rejection_counts("arena".to_string(), Some("player.testnet".to_string())));
+        assert_eq!(RejectionCounts::default(), contract.get_rejection_counts("arena".to_string(), Some("other.testnet".to_string())));
+        // other boards accept any score, on their own ranking
+        call_context("jane.testnet", DAY, 0);
+        assert!(contract.submit_score("player.testnet".to_string(), None, "race".to_string(), i32::MAX, None));
+        assert_eq!(i32::MAX, ranked_score(&contract, "race", "player.testnet"));
+        assert_eq!(0, contract.get_top_players()[0].score);
+    }
+
+    #[test]
+    fn test_default_board_rules() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_board_rules(DEFAULT_BOARD_ID.to_string(), Some(SANE_RULES));
+        assert!(!contract.save_new_score("player.testnet".to_string(), None, i32::MAX));
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 100));
+        call_context("jane.testnet", 60 * SECOND, 0);
+        assert!(!contract.save_new_score("player.testnet".to_string(), None, 900));
+        assert_eq!(100, contract.get_top_players()[0].score);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can call this method.")]
+    fn test_rejection_counts_not_moderator() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.add_moderator("moderator.testnet".to_string());
+        call_context("player.testnet", 0, 0);
+        contract.get_rejection_counts("arena".to_string(), None);
+    }
 
-fn mut_to_sorted_vec(list: &mut Vec<(String, Profile)>, only_highest: bool) -> Vec<(String, Profile)> {
-    let mut vec: Vec<(String, Profile)> = vec![];
-    while list.len() > 0 {
-        let mut profile: Profile = Profile::new(String::new());
-        let mut max_at_index = 0;
-        for index in 0..list.len() {
-            let value: &Profile = &list[index].1;
-            if profile.get_score() == 0 || profile.get_score() < value.get_score() {
-                profile = Profile{
-                    username: value.get_username(),
-                    score: value.score,
-                    hourly_reward_at: value.hourly_reward_at,
-                };
-                max_at_index = index;
-            }
-        }
+    const OUTLIER_RULES: OutlierRules = OutlierRules { max_deviations: 3.0, min_samples: 4, new_account_submissions: 0 };
 
-        list.remove(max_at_index);
-        vec.push((profile.get_username(), profile));
-        if only_highest {
-            break;
+    #[test]
+    fn test_board_distribution() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        for score in [10, 12, 8, 10] {
+            submit_arena_score(&mut contract, 0, score);
         }
+        let distribution = contract.get_board_distribution("arena".to_string()).unwrap();
+        assert_eq!((4, 10.0, 2.0), (distribution.count, distribution.mean, distribution.variance()));
+        // 8 to 15
+        assert_eq!(4, distribution.histogram[4]);
+        assert_eq!(HISTOGRAM_BUCKETS, distribution.histogram.len());
     }
 
-    return vec;
-}
-// --- END: Utility --- //
+    #[test]
+    fn test_review_outliers() {
+        let mut contract = contract_with_players(&["player.testnet", "cheater.testnet"]);
+        contract.add_moderator("moderator.testnet".to_string());
+        contract.set_outlier_rules(Some(OUTLIER_RULES));
+        for score in [10, 12, 8, 10] {
+            submit_arena_score(&mut contract, 0, score);
+        }
+        assert!(!contract.submit_score("cheater.testnet".to_string(), None, "arena".to_string(), 500, None));
+        assert!(!contract.submit_score("cheater.testnet".to_string(), None, "arena".to_string(), 900, None));
+        assert_eq!(OutlierReason::Deviation, contract.get_pending_review(0).unwrap().reason);
+        assert_eq!(0, ranked_score(&contract, "arena", "cheater.testnet"));
+        assert_eq!(4, contract.get_board_distribution("arena".to_string()).unwrap().count);
+        // held scores are not saved until approved
+        assert!(contract.get_personal_bests("cheater.testnet".to_string(), None).is_empty());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::MockedBlockchain;
-    use near_sdk::{testing_env, VMContext};
+        call_context("moderator.testnet", 0, 0);
+        contract.reject_score(1);
+        assert!(contract.approve_score(0));
+        assert_eq!(500, ranked_score(&contract, "arena", "cheater.testnet"));
+        assert_eq!(5, contract.get_board_distribution("arena".to_string()).unwrap().count);
+        assert!(contract.get_pending_reviews(0, 10).is_empty());
+        assert_eq!(vec![500], contract.get_score_history("cheater.testnet".to_string(), None, 0, 10).into_iter()
+            .map(|record| record.score).collect::<Vec<i32>>());
+    }
 
-    fn get_context(input: Vec<u8>, is_view: bool) -> VMContext {
-        VMContext {
-            current_account_id: "alice.testnet".to_string(),
-            signer_account_id: "robert.testnet".to_string(),
-            signer_account_pk: vec![0, 1, 2],
-            predecessor_account_id: "jane.testnet".to_string(),
-            input,
-            block_index: 0,
-            block_timestamp: 0,
-            account_balance: 0,
-            account_locked_balance: 0,
-            storage_usage: 0,
-            attached_deposit: 0,
-            prepaid_gas: 10u64.pow(18),
-            random_seed: vec![0, 1, 2],
-            is_view,
-            output_data_receivers: vec![],
-            epoch_height: 19,
+    #[test]
+    fn test_approved_score_in_top_is_provisional() {
+        let mut contract = contract_with_players(&["player.testnet", "cheater.testnet"]);
+        contract.add_moderator("moderator.testnet".to_string());
+        contract.set_outlier_rules(Some(OUTLIER_RULES));
+        for score in [10, 12, 8, 10] {
+            submit_arena_score(&mut contract, 0, score);
         }
+        contract.set_challenge_rules(CHALLENGE_TOP_2);
+        assert!(!contract.submit_score("cheater.testnet".to_string(), None, "arena".to_string(), 500, None));
+        call_context("moderator.testnet", 0, 0);
+        assert!(contract.approve_score(0));
+        assert_eq!(0, ranked_score(&contract, "arena", "cheater.testnet"));
+        assert_eq!(500, contract.get_provisional_score(0).unwrap().score);
     }
 
-    fn get_test_map() -> UnorderedMap<String, Profile> {
-        let mut map:UnorderedMap<String, Profile> = UnorderedMap::new(b"test".to_vec());
-        for i in [1,2,3,9] {
-            map.insert(&i.to_string(), &Profile{
-                hourly_reward_at: 0,
-                score: i,
-                username: i.to_string(),
-            });
-        };
+    #[test]
+    fn test_outliers_of_identical_scores() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.set_outlier_rules(Some(OUTLIER_RULES));
+        for score in [10, 10, 10, 10] {
+            submit_arena_score(&mut contract, 0, score);
+        }
+        // within 3 points of the mean
+        assert!(submit_arena_score(&mut contract, 0, 13));
+        assert!(contract.get_pending_reviews(0, 10).is_empty());
+    }
 
-        return map;
+    #[test]
+    fn test_review_new_account_in_top() {
+        let mut contract = contract_with_players(&["player.testnet", "newcomer.testnet", "veteran.testnet"]);
+        for score in [10, 12, 8, 10] {
+            submit_arena_score(&mut contract, 0, score);
+        }
+        contract.set_outlier_rules(Some(OutlierRules { max_deviations: 100.0, min_samples: 4, new_account_submissions: 3 }));
+        // not in the top, or not new
+        assert!(contract.submit_score("newcomer.testnet".to_string(), None, "arena".to_string(), 9, None));
+        for score in [5, 6, 7] {
+            contract.submit_score("veteran.testnet".to_string(), None, "arena".to_string(), score, None);
+        }
+        assert!(contract.submit_score("veteran.testnet".to_string(), None, "arena".to_string(), 14, None));
+
+        assert!(!contract.submit_score("newcomer.testnet".to_string(), None, "arena".to_string(), 15, None));
+        let review = contract.get_pending_review(0).unwrap();
+        assert_eq!(("newcomer.testnet".to_string(), OutlierReason::NewAccountTop), (review.account_id, review.reason));
+        assert_eq!(1, contract.get_pending_reviews(0, 10).len());
+        assert_eq!(9, ranked_score(&contract, "arena", "newcomer.testnet"));
     }
 
     #[test]
-    fn test_all() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let mut contract = ProfileState::new();
-        let account1: &str = "viigstar-2.testnet";
-        contract.grant_access(account1.to_string());
-        contract.grant_access(account1.to_string());
-        contract.revoke_access("viigstar-nft.testnet".to_string());
-        contract.revoke_access(account1.to_string());
+    #[should_panic(expected = "Only moderators can call this method.")]
+    fn test_approve_score_not_moderator() {
+        let mut contract = contract_with_players(&["player.testnet"]);
+        contract.submit_score("player.testnet".to_string(), None, "arena".to_string(), 500, None);
+        contract.approve_score(0);
     }
 
     #[test]
-    fn test_grant_access() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
+    fn test_privacy_settings() {
+        testing_env!(get_context(vec![], false));
         let mut contract = ProfileState::new();
-        let account1: &str = "viigstar-2.testnet";
-        let escrow_hash = env::sha256(account1.as_bytes());
-        contract.grant_access(account1.to_string());
-        assert_eq!(true, contract.check_access(account1.to_string()));
-        assert_eq!(1, contract.state.get(&escrow_hash).unwrap().len());
-        for key in contract.state.get(&escrow_hash).unwrap().keys() {
-            assert_eq!(account1.to_string(), key);
-        }
-        for mut user in contract.get_list_user(account1.to_string()) {
-            assert_eq!(account1.to_string(), user.username);
-            let reward_at = Utc::now().timestamp_millis();
-            user.rewarded(reward_at);
-            assert_eq!(reward_at, user.hourly_reward_at);
-            println!("rewarded at {}", reward_at);
+        for account_id in ["player.testnet", "rival.testnet"] {
+            contract.grant_access(account_id.to_string());
+            player_context(account_id);
+            assert!(contract.set_info(account_id.to_string(), Some(30), Some("Trung".to_string()), None));
         }
+        player_context("jane.testnet");
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 50));
+        assert!(contract.save_new_score("rival.testnet".to_string(), None, 20));
+
+        contract.grant_access("support.testnet".to_string());
+        player_context("support.testnet");
+        contract.send_friend_request("player.testnet".to_string());
+        player_context("player.testnet");
+        contract.accept_friend_request("support.testnet".to_string());
+        contract.set_privacy(PrivacySettings { info: Visibility::FriendsOnly, rank: Visibility::Hidden });
+
+        // anyone
+        let info = contract.get_info("player.testnet".to_string()).unwrap();
+        assert_eq!((String::new(), None, 50), (info.first_name, info.age, info.score));
+        assert_eq!(None, contract.introduce("player.testnet".to_string()));
+        let top_players = contract.get_top_players();
+        assert_eq!(vec!["rival.testnet".to_string(), "support.testnet".to_string()], top_players.into_iter().map(|entry| entry.account_id).collect::<Vec<AccountId>>());
+
+        // the player and its friends
+        assert_eq!(Some(1), contract.get_own_rank());
+        assert_eq!("Trung", contract.get_info_as_caller("player.testnet".to_string()).unwrap().first_name);
+        player_context("support.testnet");
+        assert_eq!("Trung", contract.get_info_as_caller("player.testnet".to_string()).unwrap().first_name);
+        player_context("rival.testnet");
+        assert_eq!("", contract.get_info_as_caller("player.testnet".to_string()).unwrap().first_name);
+        assert_eq!(Some(2), contract.get_own_rank());
+
+        player_context("player.testnet");
+        contract.set_privacy(PrivacySettings::default());
+        assert_eq!(3, contract.get_top_players().len());
+        assert!(contract.introduce("player.testnet".to_string()).is_some());
     }
 
-    #[test]
-    fn test_revoke_access() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
+    fn attribute_contract() -> ProfileState {
+        testing_env!(get_context(vec![], false));
         let mut contract = ProfileState::new();
-        let account1: &str = "viigstar-2.testnet";
-        contract.grant_access(account1.to_string());
-        assert_eq!(true, contract.check_access(account1.to_string()));
-        contract.revoke_access(account1.to_string());
-        assert_eq!(false, contract.check_access(account1.to_string()));
+        contract.set_attribute_schema("bio".to_string(), AttributeSchema { kind: AttributeKind::Text, max_length: 10 });
+        contract.set_attribute_schema("level".to_string(), AttributeSchema { kind: AttributeKind::Integer, max_length: 4 });
+        contract.set_attribute_schema("avatar".to_string(), AttributeSchema { kind: AttributeKind::Url, max_length: 64 });
+        contract.grant_access("player.testnet".to_string());
+        player_context("player.testnet");
+        contract
     }
 
     #[test]
-    fn test_flat_users() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let map = get_test_map();
-        let mut contract = ProfileState::new();
-        let escrow_hash = env::sha256("1".as_bytes());
-        contract.state.insert(&escrow_hash, &map);
-        assert_eq!(4, contract.flat_users().len());
-        assert_eq!("1".to_string(), contract.flat_users()[0].0);
-        assert_eq!("9".to_string(), contract.flat_users()[3].0);
+    fn test_set_attributes() {
+        let mut contract = attribute_contract();
+        let attributes: HashMap<String, String> = [
+            ("bio", " Hi there "),
+            ("level", " 42"),
+            ("avatar", "https://example.com/a.png"),
+        ].iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        assert!(contract.set_attributes("player.testnet".to_string(), attributes));
+        let info = contract.get_info("player.testnet".to_string()).unwrap();
+        assert_eq!(Some(&"Hi there".to_string()), info.attributes.get("bio"));
+        assert_eq!(Some(&"42".to_string()), info.attributes.get("level"));
+
+        assert!(contract.remove_attributes("player.testnet".to_string(), vec!["bio".to_string()]));
+        assert_eq!(2, contract.get_info("player.testnet".to_string()).unwrap().attributes.len());
+        // values of a key removed from the schema are hidden
+        player_context("jane.testnet");
+        contract.remove_attribute_schema("level".to_string());
+        assert_eq!(vec!["avatar".to_string()], contract.get_info("player.testnet".to_string()).unwrap().attributes.into_keys().collect::<Vec<String>>());
     }
 
     #[test]
-    fn test_get_username() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let mut contract = ProfileState::new();
-        let account1: &str = "viigstar-2.testnet";
-        let escrow_hash = env::sha256(account1.as_bytes());
-        contract.grant_access(account1.to_string());
-        for profile in contract.state.get(&escrow_hash).unwrap().values() {
-            assert_eq!(account1.to_string(), profile.get_username());
-        }
+    #[should_panic(expected = "Unknown attribute country.")]
+    fn test_set_unknown_attribute() {
+        let mut contract = attribute_contract();
+        contract.set_attributes("player.testnet".to_string(), [("country".to_string(), "VN".to_string())].into_iter().collect());
     }
 
     #[test]
-    fn test_get_score() {
-        // set up the mock context into the testing environment
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let contract = Profile::new(String::new());
-        assert_eq!(0, contract.get_score());
-        println!("test get_score succeeded");
+    #[should_panic(expected = "Attribute level can not be longer than 4 characters.")]
+    fn test_set_attribute_too_long() {
+        let mut contract = attribute_contract();
+        contract.set_attributes("player.testnet".to_string(), [("level".to_string(), "12345".to_string())].into_iter().collect());
     }
 
     #[test]
-    fn test_set_score() {
-        // set up the mock context into the testing environment
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let mut contract = Profile::new(String::new());
-        contract.set_score(28);
-        assert_eq!(28, contract.get_score());
-        println!("test set_score succeeded");
+    #[should_panic(expected = "Attribute level should be an integer.")]
+    fn test_set_attribute_wrong_type() {
+        let mut contract = attribute_contract();
+        contract.set_attributes("player.testnet".to_string(), [("level".to_string(), "high".to_string())].into_iter().collect());
     }
 
     #[test]
-    fn test_save_new_score() {
-        // set up the mock context into the testing environment
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let account_ref = "1";
-        let map = get_test_map();
-        let mut contract = ProfileState::new();
-        contract.state.insert(&env::sha256(account_ref.as_bytes()), &map);
-        // assert_eq!(10, contract.get_reward(account_ref.to_string()));
-        assert_eq!(true, contract.save_new_score(account_ref.to_string(), 22));
-        assert_eq!(50, contract.get_reward(account_ref.to_string()));
+    #[should_panic(expected = "Only contract owner can call this method.")]
+    fn test_set_attribute_schema_not_owner() {
+        let mut contract = attribute_contract();
+        contract.set_attribute_schema("country".to_string(), AttributeSchema { kind: AttributeKind::Text, max_length: 2 });
     }
 
     #[test]
-    fn test_is_empty() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let contract = Profile::new(String::new());
-        assert_eq!(true, contract.is_empty());
-        println!("test is_empty succeeded");
+    fn test_display_names() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.grant_access("rival.testnet".to_string());
+        contract.reserve_display_names(vec!["Admin".to_string()]);
+        assert!(contract.save_new_score("player.testnet".to_string(), None, 20));
+        player_context("player.testnet");
+        contract.set_display_name(" Bob   the_1st ".to_string());
+        assert_eq!(Some("Bob the_1st".to_string()), contract.get_display_name("player.testnet".to_string()));
+        // case-insensitive and confusable-aware lookup
+        assert_eq!(Some("player.testnet".to_string()), contract.get_account_by_display_name("B0B THE_lst".to_string()));
+        assert_eq!(vec![
+            LeaderBoardEntry { account_id: "player.testnet".to_string(), character: "player.testnet".to_string(), display_name: Some("Bob the_1st".to_string()), avatar: None, score: 20 },
+            LeaderBoardEntry { account_id: "rival.testnet".to_string(), character: "rival.testnet".to_string(), display_name: None, avatar: None, score: 0 },
+        ], contract.get_top_players());
+
+        // reserved names can only be assigned by the owner
+        player_context("jane.testnet");
+        contract.assign_display_name("rival.testnet".to_string(), "admin".to_string());
+        assert_eq!(Some("rival.testnet".to_string()), contract.get_account_by_display_name("ADMIN".to_string()));
+
+        // the name is free again once the profile is revoked
+        contract.revoke_access("player.testnet".to_string());
+        assert_eq!(None, contract.get_account_by_display_name("Bob the_1st".to_string()));
+        assert_eq!(None, contract.get_display_name("player.testnet".to_string()));
     }
 
     #[test]
-    fn test_get_sorted_profiles_by_score() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let map = get_test_map();
-        println!("length before mutate reference {}", map.len());
-        let result = get_sorted_profiles_by_score(&map, false);
-        println!("length after mutate reference {}", map.len());
-        assert_eq!(9, result[0].1.score);
-        assert_eq!(3, result[1].1.score);
-        assert_eq!(2, result[2].1.score);
-        assert_eq!(1, result[3].1.score);
+    #[should_panic(expected = "Display name is already taken.")]
+    fn test_display_name_confusable_taken() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.grant_access("rival.testnet".to_string());
+        player_context("player.testnet");
+        contract.set_display_name("paypal".to_string());
+        player_context("rival.testnet");
+        // the first "a" is a cyrillic letter
+        contract.set_display_name("P\u{430}yPa1".to_string());
     }
 
     #[test]
-    fn test_get_top_players() {
-        let context = get_context(vec![], false);
+    #[should_panic(expected = "Display name is reserved.")]
+    fn test_display_name_reserved() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.reserve_display_names(vec!["Moderator".to_string()]);
+        player_context("player.testnet");
+        contract.set_display_name("moderator".to_string());
+    }
+
+    fn rename_context(block_timestamp: u64, attached_deposit: Balance) {
+        let mut context = get_context(vec![], false);
+        context.predecessor_account_id = "player.testnet".to_string();
+        context.storage_usage = env::storage_usage();
+        context.account_balance = 1_000;
+        context.block_timestamp = block_timestamp;
+        context.attached_deposit = attached_deposit;
         testing_env!(context);
-        let map = get_test_map();
-        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
-        for i in [4,5,7,11] {
-            map2.insert(&i.to_string(), &Profile{
-                hourly_reward_at: 0,
-                score: i,
-                username: i.to_string(),
-            });
-        };
+    }
 
+    #[test]
+    fn test_display_name_renames() {
+        testing_env!(get_context(vec![], false));
         let mut contract = ProfileState::new();
-        contract.state.insert(&env::sha256("1".as_bytes()), &map);
-        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
-        let mut flat_users = contract.flat_users();
-        assert_eq!(8, flat_users.len());
-        let sorted_flat = mut_to_sorted_vec(&mut flat_users, false);
-        assert_eq!(8, sorted_flat.len());
-        assert_eq!(11, sorted_flat[0].1.score);
-        assert_eq!(9, sorted_flat[1].1.score);
-        assert_eq!(1, sorted_flat[7].1.score);
+        contract.grant_access("player.testnet".to_string());
+        contract.set_rename_rules(U128(100), 1_000);
+        assert_eq!((U128(100), 1_000), contract.get_rename_rules());
+        rename_context(0, 0);
+        contract.set_display_name("first".to_string());
+        // paid rename during the cooldown
+        rename_context(10, 100);
+        contract.set_display_name("second".to_string());
+        // free rename once the cooldown is over
+        rename_context(1_010, 0);
+        contract.set_display_name("third".to_string());
+        assert_eq!(None, contract.get_account_by_display_name("first".to_string()));
+        assert_eq!(None, contract.get_account_by_display_name("second".to_string()));
+        assert_eq!(Some("third".to_string()), contract.get_display_name("player.testnet".to_string()));
     }
 
     #[test]
-    fn test_get_reward() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let map = get_test_map();
-        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
-        for i in [4,5,7,45] {
-            map2.insert(&i.to_string(), &Profile{
-                hourly_reward_at: 0,
-                score: i,
-                username: i.to_string(),
-            });
-        };
+    #[should_panic(expected = "Renaming before the cooldown is over costs 100 yoctoNEAR.")]
+    fn test_display_name_rename_during_cooldown() {
+        testing_env!(get_context(vec![], false));
+        let mut contract = ProfileState::new();
+        contract.grant_access("player.testnet".to_string());
+        contract.set_rename_rules(U128(100), 1_000);
+        rename_context(0, 0);
+        contract.set_display_name("first".to_string());
+        rename_context(999, 99);
+        contract.set_display_name("second".to_string());
+    }
 
+    #[test]
+    #[should_panic(expected = "Display names can only contain letters, digits, spaces, '_' and '-'.")]
+    fn test_display_name_forbidden_characters() {
+        testing_env!(get_context(vec![], false));
         let mut contract = ProfileState::new();
-        let first_player:&str = "1".as_ref();
-        let second_player = String::from("4");
-        contract.state.insert(&env::sha256(first_player.to_string().as_bytes()), &map);
-        assert_eq!(10, contract.get_reward(first_player.to_string()));
+        contract.grant_access("jane.testnet".to_string());
+        contract.set_display_name("jane.testnet".to_string());
+    }
 
-        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
-        assert_eq!(50, contract.get_reward(second_player.to_string()));
-        assert_eq!(0, contract.get_reward(second_player.to_string()));
+    #[test]
+    fn test_migrate_leaderboard() {
+        testing_env!(get_context(vec![], false));
+        let mut old_state: UnorderedMap<AccountIdHash, UnorderedMap<String, Profile>> = UnorderedMap::new(b"grant".to_vec());
+        old_state.insert(&env::sha256("1".as_bytes()), &get_test_map());
+        env::state_write(&LegacyLeaderBoardState { state: old_state });
+
+        let mut contract = ProfileState::migrate();
+        assert!(contract.check_access("1".to_string()));
+        assert_eq!(9, contract.get_top_players()[0].score);
+        assert_eq!(9, contract.get_info("1".to_string()).unwrap().score);
+        player_context("1");
+        assert!(contract.set_info("1".to_string(), Some(30), Some("One".to_string()), None));
+        assert_eq!("One", contract.get_info("1".to_string()).unwrap().first_name);
     }
 
     #[test]
-    fn test_check_time_reward() {
-        let context = get_context(vec![], false);
-        testing_env!(context);
-        let map = get_test_map();
-        let mut map2:UnorderedMap<String, Profile> = UnorderedMap::new(b"test2".to_vec());
-        for i in [4,5,7,45] {
-            map2.insert(&i.to_string(), &Profile{
-                hourly_reward_at: 0,
-                score: i,
-                username: i.to_string(),
+    fn test_migrate_from_profile_v2() {
+        testing_env!(get_context(vec![], false));
+        let mut old_state: UnorderedMap<AccountIdHash, LegacyIdentity> = UnorderedMap::new(b"grant".to_vec());
+        for (name, age) in [("a", 20), ("b", -3), ("c", 0)] {
+            old_state.insert(&env::sha256(name.as_bytes()), &LegacyIdentity {
+                first_name: format!(" {}  {} ", name, name),
+                last_name: String::new(),
+                age,
             });
-        };
+        }
+        env::state_write(&LegacyProfileV2State { state: old_state });
 
-        let mut contract = ProfileState::new();
-        let first_player:&str = "1".as_ref();
-        let second_player = String::from("4");
-        contract.state.insert(&env::sha256(first_player.to_string().as_bytes()), &map);
-        // not rewarded yet
-        assert_eq!(false, contract.is_recently_rewarded(first_player.to_string()));
-        contract.state.insert(&env::sha256("4".as_bytes()), &map2);
-        assert_eq!(false, contract.is_recently_rewarded(first_player.to_string()));
+        let mut contract = ProfileState::migrate_from_profile_v2();
+        // readable before being moved, even without a leaderboard profile
+        assert_eq!("a a", contract.get_info("a".to_string()).unwrap().first_name);
+        assert!(!contract.check_access("a".to_string()));
+        contract.grant_access("a".to_string());
+        assert!(contract.save_new_score("a".to_string(), None, 12));
 
-        // rewarded correct value in range
-        assert_eq!(10, contract.get_reward(first_player.to_string()));
-        assert_eq!(50, contract.get_reward(second_player.to_string()));
+        assert_eq!(1, contract.migrate_identities(2));
+        assert_eq!(0, contract.migrate_identities(2));
+        assert_eq!(0, contract.migrate_identities(2));
+        assert_eq!(3, contract.identities.len());
+        let info = contract.get_info("a".to_string()).unwrap();
+        assert_eq!(("a a".to_string(), Some(20), 12), (info.first_name, info.age, info.score));
+        // invalid ages of profile_v2 are dropped
+        assert_eq!(None, contract.get_info("b".to_string()).unwrap().age);
+        assert_eq!("c c", contract.get_info("c".to_string()).unwrap().first_name);
+    }
 
-        // rewarded check
-        assert_eq!(true, contract.is_recently_rewarded(first_player.to_string()));
-        assert_eq!(true, contract.is_recently_rewarded(second_player.to_string()));
+    #[test]
+    fn test_import_identities() {
+        let mut contract = contract_with_players(&["a", "b"]);
+        assert!(contract.save_new_score("a".to_string(), None, 12));
+        player_context("b");
+        assert!(contract.set_info("b".to_string(), Some(30), Some("Bee".to_string()), None));
+
+        player_context("jane.testnet");
+        let identities = serde_json::from_str(r#"[
+            ["a", {"first_name": " A ", "last_name": "Aa", "age": 20}],
+            ["b", {"first_name": "B", "last_name": "", "age": -3}]
+        ]"#).unwrap();
+        assert_eq!(1, contract.import_identities(identities));
+        let info = contract.get_info("a".to_string()).unwrap();
+        assert_eq!(("A".to_string(), "Aa".to_string(), Some(20), 12), (info.first_name, info.last_name, info.age, info.score));
+        // identities set on the leaderboard are kept
+        assert_eq!("Bee", contract.get_info("b".to_string()).unwrap().first_name);
     }
-}
 
+    #[test]
+    #[should_panic(expected = "Only contract owner can call this method.")]
+    fn test_import_identities_not_owner() {
+        let mut contract = contract_with_players(&["a"]);
+        player_context("a");
+        contract.import_identities(vec![]);
+    }
+}